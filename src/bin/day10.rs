@@ -1,80 +1,192 @@
+use std::env;
+
 use adventofcode2023::read_input;
 
-#[derive(PartialEq)]
-enum PipeIntersection {
-    FromUp,
-    FromDown,
-    Empty,
+const DIRECTIONS: [(isize, isize); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+const SHAPES: [(char, [usize; 2]); 6] = [
+    ('|', [0, 2]),
+    ('-', [1, 3]),
+    ('L', [0, 1]),
+    ('J', [0, 3]),
+    ('7', [2, 3]),
+    ('F', [1, 2]),
+];
+
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const GREEN: &str = "\x1b[32m";
+const RESET: &str = "\x1b[0m";
+
+fn connections(ch: char) -> Option<[usize; 2]> {
+    SHAPES
+        .iter()
+        .find(|(shape, _)| *shape == ch)
+        .map(|(_, directions)| *directions)
 }
 
-struct Position {
-    x: usize,
-    y: usize,
-    prev_x: usize,
-    prev_y: usize,
+pub struct PipeLoop {
+    pub map: Vec<Vec<char>>,
+    pub tiles: Vec<(usize, usize)>,
+    pub start_shape: char,
+    on_loop: Vec<Vec<bool>>,
 }
 
-impl Position {
-    fn apply(&mut self, map: &Vec<Vec<char>>) {
-        match map[self.y][self.x] {
-            '|' => {
-                if self.prev_y < self.y {
-                    self.y += 1;
-                    self.prev_y += 1;
-                } else {
-                    self.y -= 1;
-                    self.prev_y -= 1;
-                }
+impl PipeLoop {
+    fn neighbour(
+        map: &[Vec<char>],
+        (x, y): (usize, usize),
+        direction: usize,
+    ) -> Option<(usize, usize)> {
+        let (dx, dy) = DIRECTIONS[direction];
+        let x = x.checked_add_signed(dx)?;
+        let y = y.checked_add_signed(dy)?;
+        if y < map.len() && x < map[y].len() {
+            Some((x, y))
+        } else {
+            None
+        }
+    }
+
+    fn trace(
+        map: &[Vec<char>],
+        start: (usize, usize),
+        mut direction: usize,
+    ) -> Option<(Vec<(usize, usize)>, usize)> {
+        let mut tiles = vec![start];
+        let mut position = start;
+        loop {
+            position = Self::neighbour(map, position, direction)?;
+            let back = (direction + 2) % 4;
+            if position == start {
+                return Some((tiles, back));
             }
-            '-' => {
-                if self.prev_x < self.x {
-                    self.x += 1;
-                    self.prev_x += 1;
-                } else {
-                    self.x -= 1;
-                    self.prev_x -= 1;
-                }
+            let [a, b] = connections(map[position.1][position.0])?;
+            direction = match back {
+                _ if back == a => b,
+                _ if back == b => a,
+                _ => return None,
+            };
+            tiles.push(position);
+        }
+    }
+
+    pub fn new(map: &[Vec<char>], start: (usize, usize)) -> Result<Self, String> {
+        let mut candidates = vec![];
+        let mut returns = vec![];
+        for direction in 0..4 {
+            if returns.contains(&direction) {
+                continue;
             }
-            'L' => {
-                if self.prev_y < self.y {
-                    self.x += 1;
-                    self.prev_y += 1;
-                } else {
-                    self.y -= 1;
-                    self.prev_x -= 1;
+            if let Some((tiles, back)) = Self::trace(map, start, direction) {
+                returns.push(back);
+                let pair = [direction.min(back), direction.max(back)];
+                if let Some((shape, _)) = SHAPES.iter().find(|(_, directions)| *directions == pair)
+                {
+                    candidates.push((*shape, tiles));
                 }
             }
-            'J' => {
-                if self.prev_y < self.y {
-                    self.x -= 1;
-                    self.prev_y += 1;
+        }
+        if candidates.len() > 1 {
+            let shapes = candidates
+                .iter()
+                .map(|(shape, _)| *shape)
+                .collect::<String>();
+            return Err(format!(
+                "start tile is ambiguous, could be any of {}",
+                shapes
+            ));
+        }
+        let (start_shape, tiles) = candidates
+            .into_iter()
+            .next()
+            .ok_or_else(|| "start tile is not part of a loop".to_string())?;
+
+        let mut map = map.to_vec();
+        map[start.1][start.0] = start_shape;
+        let mut on_loop = vec![vec![false; map[0].len()]; map.len()];
+        for &(x, y) in &tiles {
+            on_loop[y][x] = true;
+        }
+        Ok(Self {
+            map,
+            tiles,
+            start_shape,
+            on_loop,
+        })
+    }
+
+    pub fn length(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn farthest(&self) -> (usize, usize) {
+        self.tiles[self.tiles.len() / 2]
+    }
+
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        self.on_loop[y][x]
+    }
+
+    pub fn interior(&self) -> usize {
+        let mut area = 0i64;
+        for (i, &(x1, y1)) in self.tiles.iter().enumerate() {
+            let (x2, y2) = self.tiles[(i + 1) % self.tiles.len()];
+            area += x1 as i64 * y2 as i64 - x2 as i64 * y1 as i64;
+        }
+        (area.unsigned_abs() as usize + 2 - self.tiles.len()) / 2
+    }
+
+    pub fn inside(&self) -> Vec<Vec<bool>> {
+        let mut result = vec![vec![false; self.map[0].len()]; self.map.len()];
+        for (y, row) in result.iter_mut().enumerate() {
+            let mut crossings = 0;
+            for (x, tile) in row.iter_mut().enumerate() {
+                if self.on_loop[y][x] {
+                    if "|LJ".contains(self.map[y][x]) {
+                        crossings += 1;
+                    }
                 } else {
-                    self.y -= 1;
-                    self.prev_x += 1;
+                    *tile = crossings % 2 == 1;
                 }
             }
-            '7' => {
-                if self.prev_x < self.x {
-                    self.y += 1;
-                    self.prev_x += 1;
-                } else {
-                    self.x -= 1;
-                    self.prev_y -= 1;
-                }
+        }
+        result
+    }
+
+    pub fn exterior(&self) -> Vec<Vec<bool>> {
+        let (width, height) = (self.map[0].len(), self.map.len());
+        let mut result = vec![vec![false; width]; height];
+        let mut stack = vec![];
+        for y in 0..height {
+            stack.push((0, y));
+            stack.push((width - 1, y));
+        }
+        for x in 0..width {
+            stack.push((x, 0));
+            stack.push((x, height - 1));
+        }
+        while let Some((x, y)) = stack.pop() {
+            if result[y][x] || self.on_loop[y][x] {
+                continue;
             }
-            'F' => {
-                if self.prev_x > self.x {
-                    self.y += 1;
-                    self.prev_x -= 1;
-                } else {
-                    self.x += 1;
-                    self.prev_y -= 1;
+            result[y][x] = true;
+            for direction in 0..4 {
+                if let Some(next) = Self::neighbour(&self.map, (x, y), direction) {
+                    stack.push(next);
                 }
             }
-            _ => {
-                panic!("Unexpected pipe configuration")
-            }
         }
+        result
+    }
+
+    pub fn is_inside(&self, x: usize, y: usize) -> bool {
+        if self.on_loop[y][x] {
+            return false;
+        }
+        let crossings = (0..x)
+            .filter(|&k| self.on_loop[y][k] && "|LJ".contains(self.map[y][k]))
+            .count();
+        crossings % 2 == 1
     }
 }
 
@@ -97,147 +209,73 @@ fn parse(input: &str) -> (Vec<Vec<char>>, usize, usize) {
     (map, start_x, start_y)
 }
 
-fn get_starting_positions(map: &Vec<Vec<char>>, x: usize, y: usize) -> Vec<Position> {
-    let mut positions = vec![];
-    if x > 0 && String::from("-FL").contains(map[y][x - 1]) {
-        positions.push(Position {
-            x: x - 1,
-            y,
-            prev_x: x,
-            prev_y: y,
-        });
-    }
-    if x < map[0].len() - 1 && String::from("-J7").contains(map[y][x + 1]) {
-        positions.push(Position {
-            x: x + 1,
-            y,
-            prev_x: x,
-            prev_y: y,
-        });
-    }
-    if y > 0 && String::from("|F7").contains(map[y - 1][x]) {
-        positions.push(Position {
-            x,
-            y: y - 1,
-            prev_x: x,
-            prev_y: y,
-        });
+pub fn box_drawing(ch: char) -> char {
+    match ch {
+        '|' => '│',
+        '-' => '─',
+        'L' => '└',
+        'J' => '┘',
+        '7' => '┐',
+        'F' => '┌',
+        '.' => ' ',
+        _ => ch,
     }
-    if y < map.len() - 1 && String::from("|LJ").contains(map[y + 1][x]) {
-        positions.push(Position {
-            x,
-            y: y + 1,
-            prev_x: x,
-            prev_y: y,
-        });
-    }
-    positions
 }
 
-fn dfs(pipe: &mut Vec<Vec<usize>>, x: usize, y: usize) {
-    if pipe[y][x] != 0 {
-        return;
-    }
-    pipe[y][x] = 2;
-    if x > 0 {
-        dfs(pipe, x - 1, y);
-    }
-    if x < pipe[0].len() - 1 {
-        dfs(pipe, x + 1, y);
-    }
-    if y > 0 {
-        dfs(pipe, x, y - 1);
-    }
-    if y < pipe.len() - 1 {
-        dfs(pipe, x, y + 1);
+pub fn render(map: &[Vec<char>]) -> String {
+    let mut result = String::new();
+    for row in map {
+        result.extend(row.iter().map(|ch| box_drawing(*ch)));
+        result.push('\n');
     }
+    result
 }
 
-fn part1(input: &str) -> i32 {
-    let mut steps = 1;
-    let (map, x, y) = parse(input);
-    let mut positions = get_starting_positions(&map, x, y);
-    loop {
-        for i in 0..positions.len() {
-            positions[i].apply(&map);
-        }
-        steps += 1;
-        if positions[0].x == positions[1].x && positions[0].y == positions[1].y {
-            return steps;
+impl PipeLoop {
+    pub fn render(&self) -> String {
+        let mut result = String::new();
+        let inside = self.inside();
+        for (y, row) in self.map.iter().enumerate() {
+            for (x, ch) in row.iter().enumerate() {
+                if self.contains(x, y) {
+                    result += &format!("{}{}{}", BOLD, box_drawing(*ch), RESET);
+                } else if inside[y][x] {
+                    result += &format!("{}•{}", GREEN, RESET);
+                } else if *ch == '.' {
+                    result.push(' ');
+                } else {
+                    result += &format!("{}{}{}", DIM, box_drawing(*ch), RESET);
+                }
+            }
+            result.push('\n');
         }
+        result
     }
 }
 
-fn part2(input: &str) -> i32 {
+fn part1(input: &str) -> usize {
     let (map, x, y) = parse(input);
-    let mut positions = get_starting_positions(&map, x, y);
-    let mut pipe = vec![vec![0; map[0].len()]; map.len()];
-    pipe[y][x] = 1;
-    for i in 0..positions.len() {
-        pipe[positions[i].y][positions[i].x] = 1;
-    }
-    loop {
-        for i in 0..positions.len() {
-            positions[i].apply(&map);
-            pipe[positions[i].y][positions[i].x] = 1;
-        }
-        if positions[0].x == positions[1].x && positions[0].y == positions[1].y {
-            break;
-        }
-    }
-    for i in 0..map.len() {
-        dfs(&mut pipe, 0, i);
-        dfs(&mut pipe, map[0].len() - 1, i);
-    }
-    for i in 0..map[0].len() {
-        dfs(&mut pipe, i, 0);
-        dfs(&mut pipe, i, map.len() - 1);
-    }
-    let mut result = 0;
-    for i in 0..map.len() {
-        for j in 0..map[0].len() {
-            if pipe[i][j] == 0 {
-                let mut count_pipes = 0;
-                let mut state = PipeIntersection::Empty;
-                for k in 0..j {
-                    if String::from("|LJ7F").contains(map[i][k]) && pipe[i][k] == 1 {
-                        if state == PipeIntersection::Empty {
-                            if map[i][k] == '|' {
-                                count_pipes += 1;
-                            } else if map[i][k] == 'L' {
-                                state = PipeIntersection::FromUp;
-                            } else if map[i][k] == 'F' {
-                                state = PipeIntersection::FromDown;
-                            }
-                        } else if state == PipeIntersection::FromUp {
-                            if String::from("7F").contains(map[i][k]) {
-                                count_pipes += 1;
-                                state = PipeIntersection::Empty;
-                            } else if String::from("LJ").contains(map[i][k]) {
-                                state = PipeIntersection::Empty;
-                            }
-                        } else if state == PipeIntersection::FromDown {
-                            if String::from("LJ").contains(map[i][k]) {
-                                count_pipes += 1;
-                                state = PipeIntersection::Empty;
-                            } else if String::from("7F").contains(map[i][k]) {
-                                state = PipeIntersection::Empty;
-                            }
-                        }
-                    }
-                }
-                if count_pipes % 2 == 1 {
-                    result += 1;
-                }
-            }
-        }
-    }
-    result
+    let pipe_loop = PipeLoop::new(&map, (x, y)).unwrap();
+    pipe_loop.length() / 2
+}
+
+fn part2(input: &str) -> usize {
+    let (map, x, y) = parse(input);
+    PipeLoop::new(&map, (x, y)).unwrap().interior()
 }
 
 fn main() {
     let input = read_input(10);
 
+    if env::args().nth(1).as_deref() == Some("render") {
+        let (map, x, y) = parse(&input);
+        match PipeLoop::new(&map, (x, y)) {
+            Ok(pipe_loop) => print!("{}", pipe_loop.render()),
+            Err(_) => print!("{}", render(&map)),
+        }
+        return;
+    }
+
     println!("{}", part1(&input));
     println!("{}", part2(&input));
 }
@@ -320,4 +358,141 @@ mod tests {
 
         assert_eq!(part2(input), 10);
     }
+
+    #[test]
+    fn test_day10_pipe_loop() {
+        let input = "
+            ..F7.
+            .FJ|.
+            SJ.L7
+            |F--J
+            LJ...
+        "
+        .trim();
+
+        let (map, x, y) = parse(input);
+        let pipe_loop = PipeLoop::new(&map, (x, y)).unwrap();
+        assert_eq!(pipe_loop.start_shape, 'F');
+        assert_eq!(pipe_loop.length(), 16);
+        assert_eq!(pipe_loop.farthest(), (4, 2));
+        assert_eq!(pipe_loop.tiles[..3], [(0, 2), (1, 2), (1, 1)]);
+        assert!(pipe_loop.contains(3, 1));
+        assert!(pipe_loop.is_inside(2, 2));
+        assert!(!pipe_loop.is_inside(4, 4));
+
+        let input = "
+            .....
+            .S-7.
+            .|.|.
+            .L-J.
+            .....
+        "
+        .trim();
+
+        let (map, x, y) = parse(input);
+        let pipe_loop = PipeLoop::new(&map, (x, y)).unwrap();
+        assert!(pipe_loop.is_inside(2, 2));
+        assert!(!pipe_loop.is_inside(0, 2));
+        assert!(!pipe_loop.is_inside(4, 2));
+
+        let input = "
+            F-7
+            |S|
+            L-J
+        "
+        .trim();
+
+        let (map, x, y) = parse(input);
+        assert_eq!(
+            PipeLoop::new(&map, (x, y)).err(),
+            Some("start tile is not part of a loop".to_string())
+        );
+
+        let input = "
+            F-7..
+            |.|..
+            L-S-7
+            ..|.|
+            ..L-J
+        "
+        .trim();
+
+        let (map, x, y) = parse(input);
+        assert_eq!(
+            PipeLoop::new(&map, (x, y)).err(),
+            Some("start tile is ambiguous, could be any of JF".to_string())
+        );
+    }
+
+    #[test]
+    fn test_day10_interior() {
+        let input = "
+            .F----7F7F7F7F-7....
+            .|F--7||||||||FJ....
+            .||.FJ||||||||L7....
+            FJL7L7LJLJ||LJ.L-7..
+            L--J.L7...LJS7F-7L7.
+            ....F-J..F7FJ|L7L7L7
+            ....L7.F7||L7|.L7L7|
+            .....|FJLJ|FJ|F7|.LJ
+            ....FJL-7.||.||||...
+            ....L---J.LJ.LJLJ...
+        "
+        .trim();
+
+        let (map, x, y) = parse(input);
+        let pipe_loop = PipeLoop::new(&map, (x, y)).unwrap();
+        let inside = pipe_loop.inside();
+        let exterior = pipe_loop.exterior();
+        let count = inside.iter().flatten().filter(|tile| **tile).count();
+        assert_eq!(count, pipe_loop.interior());
+        for (y, row) in inside.iter().enumerate() {
+            for (x, tile) in row.iter().enumerate() {
+                assert_eq!(*tile, pipe_loop.is_inside(x, y));
+                assert!(!(*tile && exterior[y][x]));
+            }
+        }
+        assert!(exterior[0][0]);
+        assert!(!exterior[2][3]);
+
+        let size = 2000;
+        let mut map = vec![vec!['-'; size]; size];
+        for row in map.iter_mut().take(size - 1).skip(1) {
+            row[0] = '|';
+            row[size - 1] = '|';
+        }
+        map[0][0] = 'S';
+        map[0][size - 1] = '7';
+        map[size - 1][0] = 'L';
+        map[size - 1][size - 1] = 'J';
+        let pipe_loop = PipeLoop::new(&map, (0, 0)).unwrap();
+        assert_eq!(pipe_loop.interior(), (size - 2) * (size - 2));
+    }
+
+    #[test]
+    fn test_day10_render() {
+        let input = "
+            -L|F7
+            7S-7|
+            L|7||
+            -L-J|
+            L|-JF
+        "
+        .trim();
+
+        let (map, x, y) = parse(input);
+        assert_eq!(render(&map), "─└│┌┐\n┐S─┐│\n└│┐││\n─└─┘│\n└│─┘┌\n");
+
+        let pipe_loop = PipeLoop::new(&map, (x, y)).unwrap();
+        let rendered = pipe_loop.render();
+        let lines = rendered.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines[1],
+            format!("{DIM}┐{RESET}{BOLD}┌{RESET}{BOLD}─{RESET}{BOLD}┐{RESET}{DIM}│{RESET}")
+        );
+        assert_eq!(
+            lines[2],
+            format!("{DIM}└{RESET}{BOLD}│{RESET}{GREEN}•{RESET}{BOLD}│{RESET}{DIM}│{RESET}")
+        );
+    }
 }
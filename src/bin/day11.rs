@@ -1,33 +1,10 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 
 use adventofcode2023::read_input;
 
-struct Galaxy {
-    x: usize,
-    y: usize,
-}
-
-impl Galaxy {
-    fn distance(
-        &self,
-        other: &Galaxy,
-        verticals: &HashSet<usize>,
-        horizontals: &HashSet<usize>,
-        empty_bonus: usize,
-    ) -> usize {
-        let mut result = self.x.abs_diff(other.x) + self.y.abs_diff(other.y);
-        for i in self.x.min(other.x) + 1..self.x.max(other.x) {
-            if !verticals.contains(&i) {
-                result += empty_bonus;
-            }
-        }
-        for i in self.y.min(other.y) + 1..self.y.max(other.y) {
-            if !horizontals.contains(&i) {
-                result += empty_bonus;
-            }
-        }
-        result
-    }
+pub struct Galaxy {
+    pub x: usize,
+    pub y: usize,
 }
 
 fn parse(input: &str) -> (Vec<Galaxy>, HashSet<usize>, HashSet<usize>) {
@@ -47,22 +24,122 @@ fn parse(input: &str) -> (Vec<Galaxy>, HashSet<usize>, HashSet<usize>) {
     (galaxies, verticals, horizontals)
 }
 
-fn process(input: &str, empty_bonus: usize) -> usize {
+fn empty_before(occupied: &HashSet<usize>) -> Vec<u128> {
+    let size = occupied.iter().max().map_or(0, |max| max + 1);
+    let mut result = vec![0; size];
+    for i in 1..size {
+        result[i] = result[i - 1] + u128::from(!occupied.contains(&(i - 1)));
+    }
+    result
+}
+
+fn axis_sum(mut values: Vec<u128>) -> u128 {
+    values.sort_unstable();
     let mut result = 0;
-    let (galaxies, verticals, horizontals) = parse(input);
-    for i in 0..galaxies.len() {
-        for j in i + 1..galaxies.len() {
-            result += galaxies[i].distance(&galaxies[j], &verticals, &horizontals, empty_bonus);
-        }
+    let mut prefix = 0;
+    for (i, value) in values.iter().enumerate() {
+        result += value * i as u128 - prefix;
+        prefix += value;
     }
     result
 }
 
-fn part1(input: &str) -> usize {
+pub struct Universe {
+    positions: Vec<(u128, u128)>,
+}
+
+impl Universe {
+    pub fn new(
+        galaxies: &[Galaxy],
+        verticals: &HashSet<usize>,
+        horizontals: &HashSet<usize>,
+        column_bonus: u64,
+        row_bonus: u64,
+    ) -> Self {
+        let columns = empty_before(verticals);
+        let rows = empty_before(horizontals);
+        let positions = galaxies
+            .iter()
+            .map(|galaxy| {
+                (
+                    galaxy.x as u128 + columns[galaxy.x] * column_bonus as u128,
+                    galaxy.y as u128 + rows[galaxy.y] * row_bonus as u128,
+                )
+            })
+            .collect();
+        Self { positions }
+    }
+
+    pub fn distance(&self, first: usize, second: usize) -> u128 {
+        let (x1, y1) = self.positions[first];
+        let (x2, y2) = self.positions[second];
+        x1.abs_diff(x2) + y1.abs_diff(y2)
+    }
+
+    pub fn total(&self) -> u128 {
+        axis_sum(self.positions.iter().map(|(x, _)| *x).collect())
+            + axis_sum(self.positions.iter().map(|(_, y)| *y).collect())
+    }
+
+    pub fn nearest(&self, galaxy: usize, k: usize) -> Vec<(usize, u128)> {
+        let mut result = (0..self.positions.len())
+            .filter(|&other| other != galaxy)
+            .map(|other| (other, self.distance(galaxy, other)))
+            .collect::<Vec<_>>();
+        result.sort_by_key(|&(other, distance)| (distance, other));
+        result.truncate(k);
+        result
+    }
+
+    pub fn farthest_pair(&self) -> Option<(usize, usize, u128)> {
+        if self.positions.len() < 2 {
+            return None;
+        }
+        let mut best = None;
+        for sign in [1, -1] {
+            let key = |i: &usize| {
+                let (x, y) = self.positions[*i];
+                x as i128 + sign * y as i128
+            };
+            let indices = 0..self.positions.len();
+            let low = indices.clone().min_by_key(key).unwrap();
+            let high = indices.max_by_key(key).unwrap();
+            let distance = self.distance(low, high);
+            if best.is_none_or(|(_, _, best)| distance > best) {
+                best = Some((low.min(high), low.max(high), distance));
+            }
+        }
+        best
+    }
+
+    pub fn histogram(&self) -> BTreeMap<u128, usize> {
+        let mut result = BTreeMap::new();
+        for i in 0..self.positions.len() {
+            for j in i + 1..self.positions.len() {
+                *result.entry(self.distance(i, j)).or_insert(0) += 1;
+            }
+        }
+        result
+    }
+}
+
+fn process(input: &str, empty_bonus: u64) -> u128 {
+    let (galaxies, verticals, horizontals) = parse(input);
+    Universe::new(
+        &galaxies,
+        &verticals,
+        &horizontals,
+        empty_bonus,
+        empty_bonus,
+    )
+    .total()
+}
+
+fn part1(input: &str) -> u128 {
     process(input, 1)
 }
 
-fn part2(input: &str) -> usize {
+fn part2(input: &str) -> u128 {
     process(input, 1000000 - 1)
 }
 
@@ -99,5 +176,30 @@ mod tests {
     fn test_day11_part2() {
         assert_eq!(process(INPUT.trim(), 9), 1030);
         assert_eq!(process(INPUT.trim(), 99), 8410);
+        assert_eq!(process(INPUT.trim(), u64::MAX), 292 + 82 * u64::MAX as u128);
+    }
+
+    #[test]
+    fn test_day11_queries() {
+        let (galaxies, verticals, horizontals) = parse(INPUT.trim());
+        let universe = Universe::new(&galaxies, &verticals, &horizontals, 1, 1);
+        assert_eq!(universe.distance(4, 8), 9);
+        assert_eq!(universe.nearest(0, 3), vec![(1, 6), (2, 6), (3, 9)]);
+        assert_eq!(universe.farthest_pair(), Some((1, 7, 19)));
+        let histogram = universe.histogram();
+        assert_eq!(histogram.keys().last(), Some(&19));
+        assert_eq!(histogram.values().sum::<usize>(), 36);
+        assert_eq!(
+            histogram.iter().map(|(d, n)| d * *n as u128).sum::<u128>(),
+            374
+        );
+
+        let rows_only = Universe::new(&galaxies, &verticals, &horizontals, 0, 9);
+        let columns_only = Universe::new(&galaxies, &verticals, &horizontals, 9, 0);
+        let flat = Universe::new(&galaxies, &verticals, &horizontals, 0, 0);
+        assert_eq!(
+            rows_only.total() + columns_only.total() - flat.total(),
+            1030
+        );
     }
 }
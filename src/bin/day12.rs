@@ -1,96 +1,272 @@
 use core::panic;
+use std::{env, process};
 
 use adventofcode2023::read_input;
+use num_bigint::BigUint;
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
-fn can_consume(plan: &Vec<char>, value: usize, position: usize) -> bool {
+fn can_consume(plan: &[char], value: usize, position: usize) -> bool {
     if value > position + 1 {
         return false;
     }
 
-    for i in position + 1 - value..=position {
-        if plan[i] == '.' {
-            return false;
-        }
+    if plan[position + 1 - value..=position].contains(&'.') {
+        return false;
     }
 
-    if position > value {
-        if plan[position - value] == '#' {
-            return false;
-        }
+    if position > value && plan[position - value] == '#' {
+        return false;
     }
 
     true
 }
 
-fn process_line(plan: &str, springs: &Vec<usize>) -> usize {
-    let mut plan = plan.chars().collect::<Vec<_>>();
-    plan.insert(0, '.');
-    let mut dp = vec![vec![0; plan.len() + 1]; springs.len() + 1];
-    for i in 0..dp[0].len() {
-        if i > 0 && plan[i - 1] == '#' {
-            break;
+pub struct Arrangements {
+    plan: Vec<char>,
+    springs: Vec<usize>,
+    dp: Vec<Vec<u128>>,
+}
+
+impl Arrangements {
+    pub fn new(plan: &str, springs: &[usize]) -> Self {
+        let mut plan = plan.chars().collect::<Vec<_>>();
+        plan.insert(0, '.');
+        let mut dp = vec![vec![0; plan.len() + 1]; springs.len() + 1];
+        for i in 0..dp[0].len() {
+            if i > 0 && plan[i - 1] == '#' {
+                break;
+            }
+            dp[0][i] = 1;
         }
-        dp[0][i] = 1;
-    }
-    for i in 1..=springs.len() {
-        let spring = springs[i - 1];
-        for j in 1..dp[i].len() {
-            match plan[j - 1] {
-                '.' => dp[i][j] = dp[i][j - 1],
-                '#' => {
-                    if can_consume(&plan, spring, j - 1) {
-                        dp[i][j] = dp[i - 1][j - spring - 1];
-                    } else {
-                        dp[i][j] = 0;
+        for i in 1..=springs.len() {
+            let spring = springs[i - 1];
+            for j in 1..dp[i].len() {
+                match plan[j - 1] {
+                    '.' => dp[i][j] = dp[i][j - 1],
+                    '#' => {
+                        if can_consume(&plan, spring, j - 1) {
+                            dp[i][j] = dp[i - 1][j - spring - 1];
+                        } else {
+                            dp[i][j] = 0;
+                        }
                     }
-                }
-                '?' => {
-                    if can_consume(&plan, spring, j - 1) {
-                        dp[i][j] = dp[i - 1][j - spring - 1] + dp[i][j - 1];
-                    } else {
-                        dp[i][j] = dp[i][j - 1];
+                    '?' => {
+                        if can_consume(&plan, spring, j - 1) {
+                            dp[i][j] = dp[i - 1][j - spring - 1] + dp[i][j - 1];
+                        } else {
+                            dp[i][j] = dp[i][j - 1];
+                        }
                     }
+                    _ => panic!("Unexpected symbol"),
                 }
-                _ => panic!("Unexpected symbol"),
             }
         }
+
+        Self {
+            plan,
+            springs: springs.to_vec(),
+            dp,
+        }
+    }
+
+    pub fn count(&self) -> u128 {
+        self.dp[self.springs.len()][self.plan.len()]
+    }
+
+    pub fn nth(&self, mut k: u128) -> Option<String> {
+        if k >= self.count() {
+            return None;
+        }
+
+        let mut result = vec!['.'; self.plan.len()];
+        let mut i = self.springs.len();
+        let mut j = self.plan.len();
+        while j > 0 {
+            if self.plan[j - 1] != '#' {
+                let skipped = self.dp[i][j - 1];
+                if k < skipped {
+                    j -= 1;
+                    continue;
+                }
+                k -= skipped;
+            }
+            let spring = self.springs[i - 1];
+            result[j - spring..j].fill('#');
+            j -= spring + 1;
+            i -= 1;
+        }
+        Some(result[1..].iter().collect())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = String> + '_ {
+        (0..self.count()).map(|k| self.nth(k).unwrap())
+    }
+
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> Option<String> {
+        match self.count() {
+            0 => None,
+            count => self.nth(rng.gen_range(0..count)),
+        }
     }
-    dp[springs.len()][plan.len()]
 }
 
-fn part1(input: &str) -> usize {
+fn process_line(plan: &str, springs: &[usize]) -> u128 {
+    Arrangements::new(plan, springs).count()
+}
+
+fn parse_line(line: &str) -> (&str, Vec<usize>) {
+    let parts = line.split(' ').collect::<Vec<_>>();
+    (
+        parts[0],
+        parts[1].split(',').map(|x| x.parse().unwrap()).collect(),
+    )
+}
+
+fn part1(input: &str) -> u128 {
     let mut result = 0;
     for line in input.lines() {
-        let parts = line.split(' ').collect::<Vec<_>>();
-        result += process_line(
-            parts[0],
-            &parts[1].split(',').map(|x| x.parse().unwrap()).collect(),
-        );
+        let (plan, springs) = parse_line(line);
+        result += process_line(plan, &springs);
     }
     result
 }
 
-fn part2(input: &str) -> usize {
-    let mut result = 0;
-    for line in input.lines() {
-        let parts = line.split(' ').collect::<Vec<_>>();
-        let plan = vec![parts[0]; 5];
-        let springs = parts[1]
-            .split(',')
-            .map(|x| x.parse().unwrap())
-            .collect::<Vec<usize>>();
-        let len = springs.len();
-        result += process_line(
-            &plan.join("?")[..],
-            &springs.into_iter().cycle().take(5 * len).collect(),
-        );
+pub fn unfolded_count(plan: &str, springs: &[usize], factor: usize, joiner: char) -> BigUint {
+    let total = springs.len() * factor;
+    let spring = |i: usize| springs[i % springs.len()];
+    let mut needed = vec![0; total + 2];
+    for i in (0..total).rev() {
+        needed[i] = spring(i) + if i + 1 < total { 1 + needed[i + 1] } else { 0 };
+    }
+
+    let mut ready = vec![BigUint::ZERO; total + 1];
+    let mut runs = (0..total)
+        .map(|i| vec![BigUint::ZERO; spring(i) + 1])
+        .collect::<Vec<_>>();
+    ready[0] = BigUint::from(1u32);
+    let (mut low, mut high) = (0, 0);
+
+    let length = (plan.len() + 1) * factor.max(1) - 1;
+    let symbols =
+        (0..factor).flat_map(|copy| (copy > 0).then_some(joiner).into_iter().chain(plan.chars()));
+    for (position, symbol) in symbols.enumerate() {
+        let (dot, hash) = match symbol {
+            '.' => (true, false),
+            '#' => (false, true),
+            '?' => (true, true),
+            _ => panic!("Unexpected symbol"),
+        };
+        high = total.min(high + 1);
+        for i in (low..=high).rev() {
+            let finished = if i > low {
+                runs[i - 1][spring(i - 1)].clone()
+            } else {
+                BigUint::ZERO
+            };
+            let started = std::mem::take(&mut ready[i]);
+            if i < total {
+                let run = &mut runs[i];
+                if hash {
+                    run.rotate_right(1);
+                    run[0] = BigUint::ZERO;
+                    run[1] = started.clone();
+                } else {
+                    run.iter_mut().for_each(|count| *count = BigUint::ZERO);
+                }
+            }
+            if dot {
+                ready[i] = started + finished;
+            }
+        }
+        let remaining = length - position - 1;
+        while low < total && needed[low + 1] > remaining {
+            low += 1;
+        }
+    }
+
+    let mut result = ready[total].clone();
+    if total > low {
+        result += &runs[total - 1][spring(total - 1)];
     }
     result
 }
 
+fn unfold(input: &str, factor: usize, joiner: char) -> BigUint {
+    input
+        .lines()
+        .map(|line| {
+            let (plan, springs) = parse_line(line);
+            unfolded_count(plan, &springs, factor, joiner)
+        })
+        .sum()
+}
+
+fn part2(input: &str) -> BigUint {
+    unfold(input, 5, '?')
+}
+
+fn run_sample(input: &str, seed: Option<&String>) -> Result<String, String> {
+    let seed = match seed {
+        Some(arg) => arg.parse().map_err(|_| format!("invalid seed {}", arg))?,
+        None => 0,
+    };
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut result = String::new();
+    for line in input.lines() {
+        let (plan, springs) = parse_line(line);
+        match Arrangements::new(plan, &springs).sample(&mut rng) {
+            Some(arrangement) => result += &format!("{}\n", arrangement),
+            None => result += &format!("{} has no valid arrangement\n", plan),
+        }
+    }
+    Ok(result)
+}
+
+fn run_unfold(
+    input: &str,
+    factor: Option<&String>,
+    joiner: Option<&String>,
+) -> Result<BigUint, String> {
+    let factor = match factor {
+        Some(arg) => arg
+            .parse()
+            .map_err(|_| format!("invalid unfold factor {}", arg))?,
+        None => 5,
+    };
+    let joiner = match joiner.map(|arg| arg.parse::<char>()) {
+        Some(Ok(joiner @ ('.' | '#' | '?'))) => joiner,
+        Some(_) => return Err(format!("invalid joiner {}", joiner.unwrap())),
+        None => '?',
+    };
+    Ok(unfold(input, factor, joiner))
+}
+
 fn main() {
     let input = read_input(12);
 
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if args.first().map(|arg| &arg[..]) == Some("sample") {
+        match run_sample(&input, args.get(1)) {
+            Ok(report) => print!("{}", report),
+            Err(error) => {
+                eprintln!("{}", error);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if args.first().map(|arg| &arg[..]) == Some("unfold") {
+        match run_unfold(&input, args.get(1), args.get(2)) {
+            Ok(count) => println!("{}", count),
+            Err(error) => {
+                eprintln!("{}", error);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
     println!("{}", part1(&input));
     println!("{}", part2(&input));
 }
@@ -101,17 +277,95 @@ mod tests {
 
     #[test]
     fn test_day12_part1() {
-        assert_eq!(process_line("???.###", &vec![1, 1, 3]), 1);
-        assert_eq!(process_line(".??..??...?##.", &vec![1, 1, 3]), 4);
-        assert_eq!(process_line("?#?#?#?#?#?#?#?", &vec![1, 3, 1, 6]), 1);
-        assert_eq!(process_line("????.#...#...", &vec![4, 1, 1]), 1);
-        assert_eq!(process_line("????.######..#####.", &vec![1, 6, 5]), 4);
-        assert_eq!(process_line("?###????????", &vec![3, 2, 1]), 10);
-        assert_eq!(process_line("?????#?????????.??", &vec![1, 4, 3]), 41);
+        assert_eq!(process_line("???.###", &[1, 1, 3]), 1);
+        assert_eq!(process_line(".??..??...?##.", &[1, 1, 3]), 4);
+        assert_eq!(process_line("?#?#?#?#?#?#?#?", &[1, 3, 1, 6]), 1);
+        assert_eq!(process_line("????.#...#...", &[4, 1, 1]), 1);
+        assert_eq!(process_line("????.######..#####.", &[1, 6, 5]), 4);
+        assert_eq!(process_line("?###????????", &[3, 2, 1]), 10);
+        assert_eq!(process_line("?????#?????????.??", &[1, 4, 3]), 41);
     }
 
     #[test]
     fn test_day12_part2() {
-        assert_eq!(part2("?###???????? 3,2,1"), 506250);
+        assert_eq!(part2("?###???????? 3,2,1"), BigUint::from(506250u32));
+    }
+
+    #[test]
+    fn test_day12_arrangements() {
+        let arrangements = Arrangements::new("?###????????", &[3, 2, 1]);
+        let all = arrangements.iter().collect::<Vec<_>>();
+        assert_eq!(all.len(), 10);
+        assert!(all.contains(&".###.##.#...".to_string()));
+        assert!(all.contains(&".###....##.#".to_string()));
+        let mut unique = all.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), 10);
+        assert_eq!(arrangements.nth(10), None);
+
+        let mut rng = StdRng::seed_from_u64(12);
+        for _ in 0..20 {
+            assert!(all.contains(&arrangements.sample(&mut rng).unwrap()));
+        }
+        assert_eq!(Arrangements::new("#.#", &[2]).sample(&mut rng), None);
+    }
+
+    #[test]
+    fn test_day12_unfold() {
+        let lines = [
+            ("???.###", vec![1, 1, 3]),
+            ("?###????????", vec![3, 2, 1]),
+            ("?#?#?#?#?#?#?#?", vec![1, 3, 1, 6]),
+            ("????.######..#####.", vec![1, 6, 5]),
+            ("#?.", vec![1]),
+        ];
+        for (plan, springs) in &lines {
+            for joiner in ['?', '.', '#'] {
+                for factor in 1..=4 {
+                    let unfolded = vec![*plan; factor].join(&joiner.to_string());
+                    let repeated = springs.repeat(factor);
+                    assert_eq!(
+                        unfolded_count(plan, springs, factor, joiner),
+                        BigUint::from(process_line(&unfolded, &repeated)),
+                    );
+                }
+            }
+        }
+        assert!(unfolded_count("?###????????", &[3, 2, 1], 300, '?') > BigUint::from(u128::MAX));
+    }
+
+    #[test]
+    fn test_day12_sample_arguments() {
+        let input = "?###???????? 3,2,1\n#.# 2";
+        assert_eq!(
+            run_sample(input, Some(&"seven".to_string())),
+            Err("invalid seed seven".to_string())
+        );
+        let report = run_sample(input, Some(&"7".to_string())).unwrap();
+        assert!(report.ends_with("\n#.# has no valid arrangement\n"));
+        assert_eq!(report, run_sample(input, Some(&"7".to_string())).unwrap());
+    }
+
+    #[test]
+    fn test_day12_unfold_arguments() {
+        let input = "?###???????? 3,2,1";
+        assert_eq!(
+            run_unfold(input, Some(&"many".to_string()), None),
+            Err("invalid unfold factor many".to_string())
+        );
+        assert_eq!(
+            run_unfold(input, None, Some(&"x".to_string())),
+            Err("invalid joiner x".to_string())
+        );
+        assert_eq!(
+            run_unfold(input, None, Some(&"??".to_string())),
+            Err("invalid joiner ??".to_string())
+        );
+        assert_eq!(run_unfold(input, None, None), Ok(BigUint::from(506250u32)));
+        assert_eq!(
+            run_unfold(input, Some(&"1".to_string()), Some(&".".to_string())),
+            Ok(BigUint::from(10u32))
+        );
     }
 }
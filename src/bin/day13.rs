@@ -1,21 +1,78 @@
-use core::panic;
-
 use adventofcode2023::read_input;
 
+#[derive(Clone)]
+pub struct BitSet {
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    pub fn new(len: usize) -> Self {
+        Self {
+            words: vec![0; len.div_ceil(64)],
+        }
+    }
+
+    pub fn set(&mut self, i: usize) {
+        self.words[i / 64] |= 1 << (i % 64);
+    }
+
+    pub fn distance(&self, other: &BitSet) -> usize {
+        self.words
+            .iter()
+            .zip(&other.words)
+            .map(|(a, b)| (a ^ b).count_ones() as usize)
+            .sum()
+    }
+
+    pub fn differences(&self, other: &BitSet) -> Vec<usize> {
+        let mut result = vec![];
+        for (index, (a, b)) in self.words.iter().zip(&other.words).enumerate() {
+            let mut word = a ^ b;
+            while word != 0 {
+                result.push(index * 64 + word.trailing_zeros() as usize);
+                word &= word - 1;
+            }
+        }
+        result
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Axis {
+    Vertical,
+    Horizontal,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct Reflection {
+    pub axis: Axis,
+    pub line: usize,
+    pub cells: Vec<[(usize, usize); 2]>,
+}
+
+impl Reflection {
+    pub fn value(&self) -> usize {
+        match self.axis {
+            Axis::Vertical => self.line,
+            Axis::Horizontal => 100 * self.line,
+        }
+    }
+}
+
 struct Pattern {
-    vertical: Vec<usize>,
-    horizontal: Vec<usize>,
+    vertical: Vec<BitSet>,
+    horizontal: Vec<BitSet>,
 }
 
 impl Pattern {
-    fn from(pattern: &Vec<Vec<char>>) -> Self {
-        let mut vertical = vec![0; pattern[0].len()];
-        let mut horizontal = vec![0; pattern.len()];
+    fn from(pattern: &[Vec<char>]) -> Self {
+        let mut vertical = vec![BitSet::new(pattern.len()); pattern[0].len()];
+        let mut horizontal = vec![BitSet::new(pattern[0].len()); pattern.len()];
         for (i, line) in pattern.iter().enumerate() {
             for (j, ch) in line.iter().enumerate() {
                 if *ch == '#' {
-                    vertical[j] |= 1 << i;
-                    horizontal[i] |= 1 << j;
+                    vertical[j].set(i);
+                    horizontal[i].set(j);
                 }
             }
         }
@@ -26,81 +83,71 @@ impl Pattern {
         }
     }
 
-    fn check(vertical: &Vec<usize>, horizontal: &Vec<usize>, skip: usize) -> Option<usize> {
-        for (coeff, array) in [vertical, horizontal].iter().enumerate() {
-            for i in 1..array.len() {
-                let mut left = i - 1;
-                let mut right = i;
-                let mut equal = true;
-                loop {
-                    if array[left] != array[right] {
-                        equal = false;
-                        break;
-                    }
-
-                    if right == array.len() - 1 || left == 0 {
+    pub fn reflections(&self, smudges: usize) -> Vec<Reflection> {
+        let mut result = vec![];
+        for (axis, array) in [
+            (Axis::Vertical, &self.vertical),
+            (Axis::Horizontal, &self.horizontal),
+        ] {
+            for line in 1..array.len() {
+                let pairs = (0..line).rev().zip(line..array.len());
+                let mut mismatches = 0;
+                for (left, right) in pairs.clone() {
+                    mismatches += array[left].distance(&array[right]);
+                    if mismatches > smudges {
                         break;
                     }
-
-                    left -= 1;
-                    right += 1;
+                }
+                if mismatches != smudges {
+                    continue;
                 }
 
-                if equal {
-                    let result = i * if coeff == 1 { 100 } else { 1 };
-                    if result != skip {
-                        return Some(result);
+                let mut cells = vec![];
+                for (left, right) in pairs {
+                    for k in array[left].differences(&array[right]) {
+                        cells.push(match axis {
+                            Axis::Vertical => [(k, left), (k, right)],
+                            Axis::Horizontal => [(left, k), (right, k)],
+                        });
                     }
                 }
+                result.push(Reflection { axis, line, cells });
             }
         }
-        None
+        result
     }
 
-    fn value(&self, smudge: bool) -> usize {
-        let original = Pattern::check(&self.vertical, &self.horizontal, 0).unwrap();
-        if smudge {
-            for i in 0..self.horizontal.len() {
-                for j in 0..self.vertical.len() {
-                    let mut horizontal = self.horizontal.clone();
-                    let mut vertical = self.vertical.clone();
-                    horizontal[i] ^= 1 << j;
-                    vertical[j] ^= 1 << i;
-                    if let Some(value) = Pattern::check(&vertical, &horizontal, original) {
-                        return value;
-                    }
-                }
-            }
-            panic!("Unexpected");
-        } else {
-            original
-        }
+    fn value(&self, smudges: usize) -> usize {
+        self.reflections(smudges)
+            .first()
+            .expect("Unexpected")
+            .value()
     }
 }
 
-fn process(input: &str, smudge: bool) -> usize {
+fn process(input: &str, smudges: usize) -> usize {
     let mut result = 0;
     let mut processed_lines = vec![];
     for line in input.lines() {
         let line = line.trim();
         if line.is_empty() {
-            result += Pattern::from(&processed_lines).value(smudge);
+            result += Pattern::from(&processed_lines).value(smudges);
             processed_lines = vec![];
         } else {
             let processed_line = line.chars().collect::<Vec<_>>();
             processed_lines.push(processed_line);
         }
     }
-    result += Pattern::from(&processed_lines).value(smudge);
+    result += Pattern::from(&processed_lines).value(smudges);
     result
 }
 
 fn part1(input: &str) -> usize {
-    process(input, false)
+    process(input, 0)
 }
 
 fn part2(input: &str) -> usize {
-    process(input, true)
+    process(input, 1)
 }
 
 fn main() {
@@ -141,4 +188,65 @@ mod tests {
     fn test_day13_part2() {
         assert_eq!(part2(INPUT.trim()), 400);
     }
+
+    #[test]
+    fn test_day13_large() {
+        let width = 150;
+        let mut lines = vec![];
+        for i in 0..70 {
+            let line = (0..width)
+                .map(|j| {
+                    if (i * i * 3 + j * j * j + 7 * i * j + j) % 11 < 5 {
+                        '#'
+                    } else {
+                        '.'
+                    }
+                })
+                .collect::<String>();
+            lines.push(line);
+        }
+        let mirrored = lines.iter().rev().cloned().collect::<Vec<_>>();
+        lines.extend(mirrored);
+        assert_eq!(part1(&lines.join("\n")), 7000);
+
+        let mut bits = BitSet::new(width);
+        bits.set(130);
+        assert_eq!(bits.differences(&BitSet::new(width)), vec![130]);
+    }
+
+    #[test]
+    fn test_day13_reflections() {
+        let lines = INPUT.trim().lines().take(7);
+        let pattern = Pattern::from(
+            &lines
+                .map(|line| line.trim().chars().collect())
+                .collect::<Vec<_>>(),
+        );
+        assert_eq!(
+            pattern.reflections(0),
+            vec![Reflection {
+                axis: Axis::Vertical,
+                line: 5,
+                cells: vec![],
+            }]
+        );
+        assert_eq!(
+            pattern.reflections(1),
+            vec![Reflection {
+                axis: Axis::Horizontal,
+                line: 3,
+                cells: vec![[(0, 0), (5, 0)]],
+            }]
+        );
+        let two = pattern.reflections(2);
+        assert!(two.iter().all(|reflection| reflection.cells.len() == 2));
+        assert_eq!(two.len(), 1);
+        assert_eq!(two[0].value(), two[0].line);
+
+        let mut row = BitSet::new(130);
+        row.set(3);
+        row.set(100);
+        assert_eq!(row.distance(&BitSet::new(130)), 2);
+        assert_eq!(row.differences(&BitSet::new(130)), vec![3, 100]);
+    }
 }
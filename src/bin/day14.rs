@@ -1,174 +1,334 @@
-use std::collections::HashMap;
+use std::{borrow::Cow, collections::HashMap, env, fmt, process};
 
 use adventofcode2023::read_input;
 
-fn load(data: &Vec<Vec<char>>) -> usize {
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Direction {
+    North,
+    West,
+    South,
+    East,
+}
+
+pub fn program(program: &str) -> Result<Vec<Direction>, String> {
+    program
+        .chars()
+        .map(|ch| match ch {
+            'N' => Ok(Direction::North),
+            'W' => Ok(Direction::West),
+            'S' => Ok(Direction::South),
+            'E' => Ok(Direction::East),
+            _ => Err(format!("unknown direction '{}' in program {}", ch, program)),
+        })
+        .collect()
+}
+
+fn mask(low: usize, high: usize) -> u64 {
+    if high - low == 64 {
+        !0
+    } else {
+        ((1 << (high - low)) - 1) << low
+    }
+}
+
+fn count_range(words: &[u64], start: usize, end: usize) -> usize {
     let mut result = 0;
-    for x in 0..data[0].len() {
-        let mut current = data.len();
-        for y in 0..data.len() {
-            match data[y][x] {
-                'O' => {
-                    result += current;
-                    current -= 1;
+    let mut i = start;
+    while i < end {
+        let high = end.min((i / 64 + 1) * 64);
+        result += (words[i / 64] & mask(i % 64, high - i / 64 * 64)).count_ones() as usize;
+        i = high;
+    }
+    result
+}
+
+fn fill_range(words: &mut [u64], start: usize, end: usize) {
+    let mut i = start;
+    while i < end {
+        let high = end.min((i / 64 + 1) * 64);
+        words[i / 64] |= mask(i % 64, high - i / 64 * 64);
+        i = high;
+    }
+}
+
+fn transpose_block(block: &mut [u64; 64]) {
+    let mut j = 32;
+    let mut m: u64 = 0x0000_0000_FFFF_FFFF;
+    while j != 0 {
+        let mut k = 0;
+        while k < 64 {
+            let t = ((block[k] >> j) ^ block[k + j]) & m;
+            block[k] ^= t << j;
+            block[k + j] ^= t;
+            k = (k + j + 1) & !j;
+        }
+        j >>= 1;
+        m ^= m << j;
+    }
+}
+
+fn transpose(lines: &[Vec<u64>], width: usize) -> Vec<Vec<u64>> {
+    let height = lines.len();
+    let mut result = vec![vec![0; height.div_ceil(64)]; width];
+    for i in 0..height.div_ceil(64) {
+        for j in 0..width.div_ceil(64) {
+            let mut block = [0; 64];
+            for (k, word) in block.iter_mut().enumerate() {
+                if let Some(line) = lines.get(i * 64 + k) {
+                    *word = line[j];
                 }
-                '#' => {
-                    current = data.len() - y - 1;
+            }
+            transpose_block(&mut block);
+            for (k, word) in block.into_iter().enumerate() {
+                if let Some(line) = result.get_mut(j * 64 + k) {
+                    line[i] = word;
                 }
-                _ => (),
             }
         }
     }
     result
 }
 
-fn calculate(data: &Vec<Vec<char>>) -> usize {
-    let mut result = 0;
-    for y in 0..data.len() {
-        for x in 0..data[0].len() {
-            if data[y][x] == 'O' {
-                result += data.len() - y;
+fn segments(walls: &[u64], length: usize) -> Vec<(usize, usize)> {
+    let mut result = vec![];
+    let mut start = 0;
+    for i in 0..=length {
+        if i == length || walls[i / 64] & (1 << (i % 64)) != 0 {
+            if start < i {
+                result.push((start, i));
             }
+            start = i + 1;
         }
     }
     result
 }
 
-fn part1(input: &str) -> usize {
-    let data = input
-        .lines()
-        .map(|x| x.trim().chars().collect::<Vec<_>>())
-        .collect::<Vec<_>>();
-    load(&data)
-}
-
-fn next_stone(
-    x: usize,
-    y: usize,
-    data: &Vec<Vec<char>>,
-    shift: (i32, i32),
-    make_step: bool,
-) -> (usize, usize) {
-    let mut x = x as i32 + if make_step { shift.0 } else { 0 };
-    let mut y = y as i32 + if make_step { shift.1 } else { 0 };
-    while x >= 0 && x < data[0].len() as i32 && y >= 0 && y < data.len() as i32 {
-        if data[y as usize][x as usize] != '#' {
-            break;
+fn pack(lines: &mut [Vec<u64>], segments: &[Vec<(usize, usize)>], toward_start: bool) {
+    for (line, segments) in lines.iter_mut().zip(segments) {
+        let counts = segments
+            .iter()
+            .map(|&(start, end)| count_range(line, start, end))
+            .collect::<Vec<_>>();
+        line.fill(0);
+        for (&(start, end), count) in segments.iter().zip(counts) {
+            if toward_start {
+                fill_range(line, start, start + count);
+            } else {
+                fill_range(line, end - count, end);
+            }
         }
-
-        x += shift.0;
-        y += shift.1;
     }
+}
 
-    (x.max(0) as usize, y.max(0) as usize)
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum View {
+    Rows,
+    Columns,
+    Both,
 }
 
-fn iterate(data: &mut Vec<Vec<char>>) {
-    for x in 0..data[0].len() {
-        let mut current = next_stone(x, 0, &data, (0, 1), false).1;
-        for y in 0..data.len() {
-            match data[y][x] {
-                'O' => {
-                    let t = data[current][x];
-                    data[current][x] = 'O';
-                    data[y][x] = t;
-                    current = next_stone(x, current, &data, (0, 1), true).1;
-                }
-                '#' => {
-                    current = next_stone(x, y, &data, (0, 1), true).1;
-                }
-                _ => (),
-            }
+#[derive(Clone)]
+pub struct Platform {
+    width: usize,
+    height: usize,
+    walls: Vec<Vec<u64>>,
+    rows: Vec<Vec<u64>>,
+    columns: Vec<Vec<u64>>,
+    view: View,
+    row_segments: Vec<Vec<(usize, usize)>>,
+    column_segments: Vec<Vec<(usize, usize)>>,
+}
+
+impl Platform {
+    fn rows(&self) -> Cow<'_, [Vec<u64>]> {
+        match self.view {
+            View::Columns => Cow::Owned(transpose(&self.columns, self.height)),
+            _ => Cow::Borrowed(&self.rows),
+        }
+    }
+
+    fn columns(&self) -> Cow<'_, [Vec<u64>]> {
+        match self.view {
+            View::Rows => Cow::Owned(transpose(&self.rows, self.width)),
+            _ => Cow::Borrowed(&self.columns),
         }
     }
 
-    for y in 0..data.len() {
-        let mut current = next_stone(0, y, &data, (1, 0), false).0;
-        for x in 0..data[0].len() {
-            match data[y][x] {
-                'O' => {
-                    let t = data[y][current];
-                    data[y][current] = 'O';
-                    data[y][x] = t;
-                    current = next_stone(current, y, &data, (1, 0), true).0;
+    pub fn tilt(&mut self, direction: Direction) {
+        match direction {
+            Direction::West | Direction::East => {
+                if self.view == View::Columns {
+                    self.rows = transpose(&self.columns, self.height);
                 }
-                '#' => {
-                    current = next_stone(x, y, &data, (1, 0), true).0;
+                pack(
+                    &mut self.rows,
+                    &self.row_segments,
+                    direction == Direction::West,
+                );
+                self.view = View::Rows;
+            }
+            Direction::North | Direction::South => {
+                if self.view == View::Rows {
+                    self.columns = transpose(&self.rows, self.width);
                 }
-                _ => (),
+                pack(
+                    &mut self.columns,
+                    &self.column_segments,
+                    direction == Direction::North,
+                );
+                self.view = View::Columns;
             }
         }
     }
 
-    for x in 0..data[0].len() {
-        let mut current = next_stone(x, data.len() - 1, &data, (0, -1), false).1;
-        for y in (0..data.len()).rev() {
-            match data[y][x] {
-                'O' => {
-                    let t = data[current][x];
-                    data[current][x] = 'O';
-                    data[y][x] = t;
-                    current = next_stone(x, current, &data, (0, -1), true).1;
+    pub fn run(&mut self, program: &[Direction], repeats: usize) {
+        let mut seen: HashMap<Vec<Vec<u64>>, usize> = HashMap::new();
+        let mut step = 0;
+        while step < repeats {
+            let state = self.rows().into_owned();
+            if let Some(&previous) = seen.get(&state) {
+                for _ in 0..(repeats - step) % (step - previous) {
+                    program.iter().for_each(|&direction| self.tilt(direction));
                 }
-                '#' => {
-                    current = next_stone(x, y, &data, (0, -1), true).1;
-                }
-                _ => (),
+                return;
             }
+            seen.insert(state, step);
+            program.iter().for_each(|&direction| self.tilt(direction));
+            step += 1;
         }
     }
 
-    for y in 0..data.len() {
-        let mut current = next_stone(data[0].len() - 1, y, &data, (-1, 0), false).0;
-        for x in (0..data[0].len()).rev() {
-            match data[y][x] {
-                'O' => {
-                    let t = data[y][current];
-                    data[y][current] = 'O';
-                    data[y][x] = t;
-                    current = next_stone(current, y, &data, (-1, 0), true).0;
-                }
-                '#' => {
-                    current = next_stone(x, y, &data, (-1, 0), true).0;
-                }
-                _ => (),
+    pub fn load(&self, edge: Direction) -> usize {
+        let (lines, width) = match edge {
+            Direction::North | Direction::South => (self.rows(), self.width),
+            Direction::West | Direction::East => (self.columns(), self.height),
+        };
+        let length = lines.len();
+        lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| {
+                let distance = match edge {
+                    Direction::North | Direction::West => length - i,
+                    Direction::South | Direction::East => i + 1,
+                };
+                count_range(line, 0, width) * distance
+            })
+            .sum()
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rows = self.rows();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let bit = 1 << (x % 64);
+                let ch = if self.walls[y][x / 64] & bit != 0 {
+                    '#'
+                } else if rows[y][x / 64] & bit != 0 {
+                    'O'
+                } else {
+                    '.'
+                };
+                write!(f, "{}", ch)?;
             }
+            writeln!(f)?;
         }
+        Ok(())
     }
 }
 
-fn part2(input: &str) -> usize {
-    let mut data = input
+fn parse(input: &str) -> Platform {
+    let lines = input
+        .trim()
         .lines()
-        .map(|x| x.trim().chars().collect::<Vec<_>>())
+        .map(|line| line.trim())
         .collect::<Vec<_>>();
-    let mut positions: HashMap<String, usize> = HashMap::new();
-    for step in 1..=1000000000 {
-        iterate(&mut data);
-        let mut key = String::new();
-        for i in 0..data.len() {
-            for j in 0..data[0].len() {
-                if data[i][j] == 'O' {
-                    key += &format!("{i}_{j}-")[..];
-                }
-            }
-        }
-        if let Some(old_steps) = positions.get(&key) {
-            let left_steps = (1000000000 - old_steps) % (step - old_steps);
-            for _ in 0..left_steps {
-                iterate(&mut data);
+    let height = lines.len();
+    let width = lines[0].len();
+    let mut walls = vec![vec![0; width.div_ceil(64)]; height];
+    let mut rocks = walls.clone();
+    for (y, line) in lines.iter().enumerate() {
+        for (x, ch) in line.chars().enumerate() {
+            match ch {
+                '#' => walls[y][x / 64] |= 1 << (x % 64),
+                'O' => rocks[y][x / 64] |= 1 << (x % 64),
+                _ => (),
             }
-            return calculate(&data);
-        } else {
-            positions.insert(key, step);
         }
     }
-    calculate(&data)
+    let row_segments = walls.iter().map(|row| segments(row, width)).collect();
+    let column_segments = transpose(&walls, width)
+        .iter()
+        .map(|column| segments(column, height))
+        .collect();
+
+    Platform {
+        width,
+        height,
+        walls,
+        columns: transpose(&rocks, width),
+        rows: rocks,
+        view: View::Both,
+        row_segments,
+        column_segments,
+    }
+}
+
+fn part1(input: &str) -> usize {
+    let mut platform = parse(input);
+    platform.tilt(Direction::North);
+    platform.load(Direction::North)
+}
+
+fn part2(input: &str) -> usize {
+    let mut platform = parse(input);
+    platform.run(&program("NWSE").unwrap(), 1000000000);
+    platform.load(Direction::North)
+}
+
+fn run_program(
+    input: &str,
+    directions: Option<&String>,
+    repeats: Option<&String>,
+) -> Result<String, String> {
+    let directions = program(directions.map_or("NWSE", |arg| &arg[..]))?;
+    let repeats = match repeats {
+        Some(arg) => arg
+            .parse()
+            .map_err(|_| format!("invalid repeat count {}", arg))?,
+        None => 1,
+    };
+    let mut platform = parse(input);
+    platform.run(&directions, repeats);
+    let mut result = platform.to_string();
+    for edge in [
+        Direction::North,
+        Direction::West,
+        Direction::South,
+        Direction::East,
+    ] {
+        result += &format!("{:?}: {}\n", edge, platform.load(edge));
+    }
+    Ok(result)
 }
 
 fn main() {
     let input = read_input(14);
 
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if args.first().map(|arg| &arg[..]) == Some("program") {
+        match run_program(&input, args.get(1), args.get(2)) {
+            Ok(report) => print!("{}", report),
+            Err(error) => {
+                eprintln!("{}", error);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
     println!("{}", part1(&input));
     println!("{}", part2(&input));
 }
@@ -199,4 +359,89 @@ mod tests {
     fn test_day14_part2() {
         assert_eq!(part2(INPUT.trim()), 64);
     }
+
+    #[test]
+    fn test_day14_bitboard() {
+        let mut platform = parse(INPUT);
+        platform.run(&program("NWSE").unwrap(), 1);
+        assert_eq!(
+            platform.to_string(),
+            ".....#....
+....#...O#
+...OO##...
+.OO#......
+.....OOO#.
+.O#...O#.#
+....O#....
+......OOOO
+#...O###..
+#..OO#....
+"
+        );
+
+        let (width, height): (usize, usize) = (130, 70);
+        let lines = (0..height)
+            .map(|y| {
+                let mut line = vec![0; width.div_ceil(64)];
+                for x in 0..width {
+                    if (x * x + 3 * y * x + y) % 7 < 3 {
+                        line[x / 64] |= 1 << (x % 64);
+                    }
+                }
+                line
+            })
+            .collect::<Vec<Vec<u64>>>();
+        let columns = transpose(&lines, width);
+        assert_eq!(columns.len(), width);
+        for x in 0..width {
+            for y in 0..height {
+                assert_eq!(
+                    columns[x][y / 64] >> (y % 64) & 1,
+                    lines[y][x / 64] >> (x % 64) & 1
+                );
+            }
+        }
+        assert_eq!(transpose(&columns, height), lines);
+    }
+
+    #[test]
+    fn test_day14_program() {
+        assert_eq!(
+            program("NNESX"),
+            Err("unknown direction 'X' in program NNESX".to_string())
+        );
+        assert_eq!(
+            run_program(INPUT, Some(&"NX".to_string()), None),
+            Err("unknown direction 'X' in program NX".to_string())
+        );
+        assert_eq!(
+            run_program(INPUT, None, Some(&"-3".to_string())),
+            Err("invalid repeat count -3".to_string())
+        );
+        let report = run_program(INPUT, Some(&"N".to_string()), Some(&"2".to_string())).unwrap();
+        assert!(report.ends_with("North: 136\nWest: 121\nSouth: 62\nEast: 77\n"));
+
+        let mut platform = parse(INPUT);
+        platform.run(&program("NWSE").unwrap(), 3);
+        let mut spins = parse(INPUT);
+        spins.run(&program("NWSENWSENWSE").unwrap(), 1);
+        assert_eq!(platform.to_string(), spins.to_string());
+        assert_eq!(platform.load(Direction::North), 69);
+
+        let mut platform = parse(INPUT);
+        platform.run(&program("NNESW").unwrap(), 1000);
+        let mut expected = parse(INPUT);
+        for _ in 0..1000 {
+            expected.run(&program("NNESW").unwrap(), 1);
+        }
+        assert_eq!(platform.to_string(), expected.to_string());
+
+        let mut platform = parse(INPUT);
+        platform.tilt(Direction::East);
+        assert_eq!(platform.load(Direction::East), 105);
+        platform.tilt(Direction::West);
+        assert_eq!(platform.load(Direction::West), 147);
+        assert_eq!(platform.load(Direction::South), 94);
+        assert_eq!(platform.load(Direction::North), 104);
+    }
 }
@@ -1,4 +1,7 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+};
 
 use adventofcode2023::read_input;
 
@@ -148,6 +151,38 @@ fn lcm(numbers: Vec<usize>) -> usize {
     result
 }
 
+fn to_dot(modules: &HashMap<String, Module>) -> String {
+    let mut names = modules.keys().collect::<Vec<_>>();
+    names.sort();
+    let mut sinks = modules
+        .values()
+        .flat_map(|module| &module.outputs)
+        .filter(|output| !modules.contains_key(*output))
+        .collect::<Vec<_>>();
+    sinks.sort();
+    sinks.dedup();
+
+    let mut result = String::from("digraph circuit {\n");
+    for name in &names {
+        let shape = match &modules[*name].module_type[..] {
+            "%" => "box",
+            "&" => "diamond",
+            _ => "doublecircle",
+        };
+        result += &format!("    \"{}\" [shape={}];\n", name, shape);
+    }
+    for sink in sinks {
+        result += &format!("    \"{}\" [shape=plaintext];\n", sink);
+    }
+    for name in &names {
+        for output in &modules[*name].outputs {
+            result += &format!("    \"{}\" -> \"{}\";\n", name, output);
+        }
+    }
+    result += "}\n";
+    result
+}
+
 fn part1(input: &str) -> usize {
     let mut modules = parse(input);
     let mut low = 0;
@@ -227,6 +262,11 @@ fn part2(input: &str) -> usize {
 fn main() {
     let input = read_input(20);
 
+    if env::args().nth(1).as_deref() == Some("dot") {
+        print!("{}", to_dot(&parse(&input)));
+        return;
+    }
+
     println!("{}", part1(&input));
     println!("{}", part2(&input));
 }
@@ -259,4 +299,26 @@ mod tests {
 
     #[test]
     fn test_day20_part2() {}
+
+    #[test]
+    fn test_day20_dot() {
+        assert_eq!(
+            to_dot(&parse(INPUT2)),
+            "digraph circuit {
+    \"a\" [shape=box];
+    \"b\" [shape=box];
+    \"broadcaster\" [shape=doublecircle];
+    \"con\" [shape=diamond];
+    \"inv\" [shape=diamond];
+    \"output\" [shape=plaintext];
+    \"a\" -> \"inv\";
+    \"a\" -> \"con\";
+    \"b\" -> \"con\";
+    \"broadcaster\" -> \"a\";
+    \"con\" -> \"output\";
+    \"inv\" -> \"b\";
+}
+"
+        );
+    }
 }
@@ -1,5 +1,8 @@
 use rand::Rng;
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, VecDeque},
+    env,
+};
 
 use adventofcode2023::read_input;
 
@@ -67,6 +70,92 @@ fn part1(input: &str) -> usize {
     }
 }
 
+fn components(edges: &HashMap<String, Vec<String>>) -> Vec<Vec<String>> {
+    let mut names = edges.keys().cloned().collect::<Vec<_>>();
+    names.sort();
+    let index = names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name, i))
+        .collect::<HashMap<_, _>>();
+    let adjacency = names
+        .iter()
+        .map(|name| edges[name].iter().map(|target| index[target]).collect())
+        .collect::<Vec<Vec<usize>>>();
+
+    for sink in 1..names.len() {
+        let mut flow: HashMap<(usize, usize), i32> = HashMap::new();
+        let mut total = 0;
+        while total <= 3 {
+            let mut parent = vec![None; names.len()];
+            parent[0] = Some(0);
+            let mut queue = VecDeque::from([0]);
+            while let Some(u) = queue.pop_front() {
+                for &v in &adjacency[u] {
+                    if parent[v].is_none() && flow.get(&(u, v)).copied().unwrap_or(0) < 1 {
+                        parent[v] = Some(u);
+                        queue.push_back(v);
+                    }
+                }
+            }
+            if parent[sink].is_none() {
+                if total == 3 {
+                    let (inside, outside) = names
+                        .iter()
+                        .zip(&parent)
+                        .partition::<Vec<_>, _>(|(_, parent)| parent.is_some());
+                    return [inside, outside]
+                        .map(|side| side.into_iter().map(|(name, _)| name.clone()).collect())
+                        .into();
+                }
+                break;
+            }
+            let mut v = sink;
+            while v != 0 {
+                let u = parent[v].unwrap();
+                *flow.entry((u, v)).or_insert(0) += 1;
+                *flow.entry((v, u)).or_insert(0) -= 1;
+                v = u;
+            }
+            total += 1;
+        }
+    }
+    vec![names]
+}
+
+fn cut(edges: &HashMap<String, Vec<String>>, component: &[String]) -> Vec<(String, String)> {
+    let mut result = vec![];
+    for vertex in component {
+        for target in &edges[vertex] {
+            if !component.contains(target) {
+                result.push((vertex.min(target).clone(), vertex.max(target).clone()));
+            }
+        }
+    }
+    result.sort();
+    result
+}
+
+fn to_dot(edges: &HashMap<String, Vec<String>>, cut: &[(String, String)]) -> String {
+    let mut pairs = edges
+        .iter()
+        .flat_map(|(source, targets)| targets.iter().map(move |target| (source, target)))
+        .filter(|(source, target)| source < target)
+        .collect::<Vec<_>>();
+    pairs.sort();
+    let mut result = String::from("graph wiring {\n");
+    for (source, target) in pairs {
+        let style = if cut.contains(&(source.clone(), target.clone())) {
+            " [color=red, penwidth=3]"
+        } else {
+            ""
+        };
+        result += &format!("    \"{}\" -- \"{}\"{};\n", source, target, style);
+    }
+    result += "}\n";
+    result
+}
+
 fn part2(input: &str) -> usize {
     0
 }
@@ -74,6 +163,18 @@ fn part2(input: &str) -> usize {
 fn main() {
     let input = read_input(25);
 
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if args.first().map(|arg| &arg[..]) == Some("dot") {
+        let (_, edges) = parse(&input);
+        let highlighted = if args.get(1).map(|arg| &arg[..]) == Some("cut") {
+            cut(&edges, &components(&edges)[0])
+        } else {
+            vec![]
+        };
+        print!("{}", to_dot(&edges, &highlighted));
+        return;
+    }
+
     println!("{}", part1(&input));
     println!("{}", part2(&input));
 }
@@ -105,4 +206,18 @@ mod tests {
 
     #[test]
     fn test_day25_part2() {}
+
+    #[test]
+    fn test_day25_dot() {
+        let (_, edges) = parse(INPUT);
+        let cut = cut(&edges, &components(&edges)[0]);
+        let expected = [("bvb", "cmg"), ("hfx", "pzl"), ("jqt", "nvd")];
+        assert_eq!(cut, expected.map(|(a, b)| (a.to_string(), b.to_string())));
+
+        let dot = to_dot(&edges, &cut);
+        assert!(dot.starts_with("graph wiring {\n"));
+        assert_eq!(dot.lines().count(), 33 + 2);
+        assert!(dot.contains("    \"bvb\" -- \"cmg\" [color=red, penwidth=3];\n"));
+        assert!(dot.contains("    \"bvb\" -- \"hfx\";\n"));
+    }
 }
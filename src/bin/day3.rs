@@ -2,11 +2,11 @@ use std::collections::HashSet;
 
 use adventofcode2023::read_input;
 
-struct Number {
-    number: u32,
-    x_min: usize,
-    x_max: usize,
-    y: usize,
+pub struct Number {
+    pub number: u32,
+    pub x_min: usize,
+    pub x_max: usize,
+    pub y: usize,
 }
 
 impl Number {
@@ -23,15 +23,103 @@ impl Number {
     }
 }
 
-fn parse(input: &str, only_stars: bool) -> (Vec<Number>, HashSet<(usize, usize)>) {
+pub struct Symbol {
+    pub symbol: char,
+    pub x: usize,
+    pub y: usize,
+}
+
+pub enum Arity {
+    Exact(usize),
+    AtLeast(usize),
+}
+
+impl Arity {
+    fn matches(&self, count: usize) -> bool {
+        match self {
+            Arity::Exact(n) => count == *n,
+            Arity::AtLeast(n) => count >= *n,
+        }
+    }
+}
+
+pub struct GearRule {
+    pub symbols: Vec<char>,
+    pub arity: Arity,
+    pub aggregate: fn(&[u32]) -> u32,
+}
+
+impl GearRule {
+    pub fn classic() -> Self {
+        Self {
+            symbols: vec!['*'],
+            arity: Arity::Exact(2),
+            aggregate: |values| values.iter().product(),
+        }
+    }
+}
+
+pub struct Schematic {
+    pub numbers: Vec<Number>,
+    pub symbols: Vec<Symbol>,
+}
+
+impl Schematic {
+    pub fn numbers_adjacent_to(&self, symbol: &Symbol) -> Vec<&Number> {
+        self.numbers
+            .iter()
+            .filter(|number| number.check(symbol.x, symbol.y))
+            .collect()
+    }
+
+    pub fn symbols_adjacent_to(&self, number: &Number) -> Vec<&Symbol> {
+        self.symbols
+            .iter()
+            .filter(|symbol| number.check(symbol.x, symbol.y))
+            .collect()
+    }
+
+    pub fn gears(&self, rule: &GearRule) -> Vec<(&Symbol, Vec<&Number>)> {
+        self.symbols
+            .iter()
+            .filter(|symbol| rule.symbols.contains(&symbol.symbol))
+            .map(|symbol| (symbol, self.numbers_adjacent_to(symbol)))
+            .filter(|(_, numbers)| rule.arity.matches(numbers.len()))
+            .collect()
+    }
+
+    pub fn gear_values(&self, rule: &GearRule) -> Vec<u32> {
+        self.gears(rule)
+            .iter()
+            .map(|(_, numbers)| {
+                let values: Vec<u32> = numbers.iter().map(|number| number.number).collect();
+                (rule.aggregate)(&values)
+            })
+            .collect()
+    }
+
+    pub fn graph(&self) -> Vec<(usize, usize)> {
+        let mut edges = vec![];
+        for (i, number) in self.numbers.iter().enumerate() {
+            for (j, symbol) in self.symbols.iter().enumerate() {
+                if number.check(symbol.x, symbol.y) {
+                    edges.push((i, j));
+                }
+            }
+        }
+        edges
+    }
+}
+
+fn parse(input: &str) -> Schematic {
     let mut numbers = vec![];
-    let mut symbols = HashSet::new();
+    let mut symbols = vec![];
 
     for (i, line) in input.lines().enumerate() {
         let line = line.trim();
         let mut current_number = 0;
         let mut number_count = 0;
-        for (j, ch) in line.trim().chars().enumerate() {
+        for (j, ch) in line.chars().enumerate() {
             if let Some(value) = ch.to_digit(10) {
                 number_count += 1;
                 current_number *= 10;
@@ -51,8 +139,12 @@ fn parse(input: &str, only_stars: bool) -> (Vec<Number>, HashSet<(usize, usize)>
                     number_count = 0;
                 }
 
-                if ch != '.' && !only_stars || ch == '*' {
-                    symbols.insert((j, i));
+                if ch != '.' {
+                    symbols.push(Symbol {
+                        symbol: ch,
+                        x: j,
+                        y: i,
+                    });
                 }
             }
         }
@@ -60,7 +152,7 @@ fn parse(input: &str, only_stars: bool) -> (Vec<Number>, HashSet<(usize, usize)>
         if number_count > 0 {
             let number = Number {
                 number: current_number,
-                x_min: line.len() - number_count - 1,
+                x_min: line.len() - number_count,
                 x_max: line.len() - 1,
                 y: i,
             };
@@ -69,44 +161,18 @@ fn parse(input: &str, only_stars: bool) -> (Vec<Number>, HashSet<(usize, usize)>
         }
     }
 
-    (numbers, symbols)
+    Schematic { numbers, symbols }
 }
 
 fn part1(input: &str) -> u32 {
-    let mut result = 0;
-    let (numbers, symbols) = parse(input, false);
-    for number in numbers {
-        for (x, y) in &symbols {
-            if number.check(*x, *y) {
-                result += number.number;
-                break;
-            }
-        }
-    }
-    result
+    let schematic = parse(input);
+    let parts: HashSet<usize> = schematic.graph().into_iter().map(|(i, _)| i).collect();
+    parts.into_iter().map(|i| schematic.numbers[i].number).sum()
 }
 
 fn part2(input: &str) -> u32 {
-    let mut result = 0;
-    let (numbers, symbols) = parse(input, true);
-    for (x, y) in symbols {
-        let mut ratio = 1;
-        let mut counter = 0;
-        for number in &numbers {
-            if number.check(x, y) {
-                ratio *= number.number;
-                counter += 1;
-            }
-
-            if counter > 2 {
-                break;
-            }
-        }
-        if counter == 2 {
-            result += ratio;
-        }
-    }
-    result
+    let schematic = parse(input);
+    schematic.gear_values(&GearRule::classic()).iter().sum()
 }
 
 fn main() {
@@ -193,4 +259,56 @@ mod tests {
 
         assert_eq!(part2(&input), 6756);
     }
+
+    #[test]
+    fn test_day3_number_at_line_end() {
+        assert_eq!(part1("..*.12\n......"), 0);
+        assert_eq!(part1("...*12\n......"), 12);
+        assert_eq!(part1("......\n..#.12"), 0);
+    }
+
+    #[test]
+    fn test_day3_schematic_queries() {
+        let input = "
+            467..114..
+            ...*......
+            ..35..633.
+            ......#...
+            617*......
+            .....+.58.
+            ..592.....
+            ......755.
+            ...$.*....
+            .664.598..
+        "
+        .trim();
+
+        let schematic = parse(input);
+        let star = &schematic.symbols[0];
+        let adjacent: Vec<u32> = schematic
+            .numbers_adjacent_to(star)
+            .iter()
+            .map(|number| number.number)
+            .collect();
+        assert_eq!(adjacent, vec![467, 35]);
+
+        let symbols: Vec<char> = schematic
+            .symbols_adjacent_to(&schematic.numbers[4])
+            .iter()
+            .map(|symbol| symbol.symbol)
+            .collect();
+        assert_eq!(symbols, vec!['*']);
+
+        let rule = GearRule {
+            symbols: vec!['*', '#', '+', '$'],
+            arity: Arity::AtLeast(1),
+            aggregate: |values| values.iter().sum(),
+        };
+        assert_eq!(
+            schematic.gear_values(&rule),
+            vec![502, 633, 617, 592, 664, 1353]
+        );
+
+        assert_eq!(schematic.graph().len(), 8);
+    }
 }
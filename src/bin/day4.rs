@@ -2,7 +2,9 @@ use adventofcode2023::read_input;
 
 use std::collections::HashSet;
 
-struct Card {
+use num_bigint::BigUint;
+
+pub struct Card {
     winning: HashSet<i32>,
     numbers: HashSet<i32>,
 }
@@ -17,16 +19,95 @@ impl Card {
         }
         result
     }
+}
+
+pub fn doubling(count: usize) -> BigUint {
+    match count {
+        0 => BigUint::ZERO,
+        x => BigUint::from(1u32) << (x - 1),
+    }
+}
+
+pub enum Overflow {
+    Truncate,
+    Wrap,
+}
+
+pub struct CopyRule {
+    pub cap: Option<usize>,
+    pub overflow: Overflow,
+}
+
+impl Default for CopyRule {
+    fn default() -> Self {
+        Self {
+            cap: None,
+            overflow: Overflow::Truncate,
+        }
+    }
+}
 
-    fn points(&self) -> i32 {
-        let count = self.count();
-        match count {
-            0 => 0,
-            x => 2i32.pow(x as u32 - 1),
+impl CopyRule {
+    fn targets(&self, index: usize, count: usize, total: usize) -> Vec<usize> {
+        let count = self.cap.map_or(count, |cap| count.min(cap));
+        match self.overflow {
+            Overflow::Truncate => (index + 1..total.min(index + 1 + count)).collect(),
+            Overflow::Wrap => (1..=count).map(|j| (index + j) % total).collect(),
         }
     }
 }
 
+#[derive(Debug, PartialEq)]
+pub struct CardCopies {
+    pub original: BigUint,
+    pub won: BigUint,
+}
+
+impl CardCopies {
+    pub fn total(&self) -> BigUint {
+        &self.original + &self.won
+    }
+}
+
+pub struct Cascade {
+    pub scoring: fn(usize) -> BigUint,
+    pub rule: CopyRule,
+}
+
+impl Default for Cascade {
+    fn default() -> Self {
+        Self {
+            scoring: doubling,
+            rule: CopyRule::default(),
+        }
+    }
+}
+
+impl Cascade {
+    pub fn points(&self, cards: &[Card]) -> BigUint {
+        cards.iter().map(|card| (self.scoring)(card.count())).sum()
+    }
+
+    // Cards are processed once in order, so copies wrapped onto an earlier card
+    // are counted for that card but do not win further copies.
+    pub fn copies(&self, cards: &[Card]) -> Vec<CardCopies> {
+        let mut copies: Vec<CardCopies> = cards
+            .iter()
+            .map(|_| CardCopies {
+                original: BigUint::from(1u32),
+                won: BigUint::ZERO,
+            })
+            .collect();
+        for (i, card) in cards.iter().enumerate() {
+            let total = copies[i].total();
+            for j in self.rule.targets(i, card.count(), cards.len()) {
+                copies[j].won += &total;
+            }
+        }
+        copies
+    }
+}
+
 fn parse(input: &str) -> Vec<Card> {
     let mut result = vec![];
     for line in input.lines() {
@@ -48,20 +129,18 @@ fn parse(input: &str) -> Vec<Card> {
     result
 }
 
-fn part1(input: &str) -> i32 {
+fn part1(input: &str) -> BigUint {
     let cards = parse(input);
-    cards.iter().map(|x| x.points()).sum()
+    Cascade::default().points(&cards)
 }
 
-fn part2(input: &str) -> i32 {
+fn part2(input: &str) -> BigUint {
     let cards = parse(input);
-    let mut multiplier = vec![1; cards.len()];
-    for (i, card) in cards.iter().enumerate() {
-        for j in 0..card.count() {
-            multiplier[i + j + 1] += multiplier[i];
-        }
-    }
-    multiplier.iter().sum()
+    Cascade::default()
+        .copies(&cards)
+        .iter()
+        .map(|copies| copies.total())
+        .sum()
 }
 
 fn main() {
@@ -87,7 +166,7 @@ mod tests {
         "
         .trim();
 
-        assert_eq!(part1(input), 13);
+        assert_eq!(part1(input), BigUint::from(13u32));
     }
 
     #[test]
@@ -102,6 +181,59 @@ mod tests {
         "
         .trim();
 
-        assert_eq!(part2(input), 30);
+        assert_eq!(part2(input), BigUint::from(30u32));
+    }
+
+    #[test]
+    fn test_day4_cascade_rules() {
+        let input = "
+            Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
+            Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19
+            Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1
+            Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83
+            Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36
+            Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11
+        "
+        .trim();
+        let cards = parse(input);
+
+        let won: Vec<BigUint> = Cascade::default()
+            .copies(&cards)
+            .into_iter()
+            .map(|copies| copies.won)
+            .collect();
+        let expected: Vec<BigUint> = [0u32, 1, 3, 7, 13, 0].map(BigUint::from).into();
+        assert_eq!(won, expected);
+
+        let linear = Cascade {
+            scoring: |count| BigUint::from(count),
+            rule: CopyRule {
+                cap: Some(1),
+                overflow: Overflow::Truncate,
+            },
+        };
+        assert_eq!(linear.points(&cards), BigUint::from(9u32));
+        let total: BigUint = linear.copies(&cards).iter().map(|c| c.total()).sum();
+        assert_eq!(total, BigUint::from(16u32));
+
+        let cards = parse("Card 1: 1 2 | 1 2\nCard 2: 3 | 3");
+        let wrap = Cascade {
+            rule: CopyRule {
+                cap: None,
+                overflow: Overflow::Wrap,
+            },
+            ..Cascade::default()
+        };
+        let copies = wrap.copies(&cards);
+        assert_eq!(copies[0].won, BigUint::from(3u32));
+        assert_eq!(copies[1].won, BigUint::from(1u32));
+
+        let cards = parse("Card 1: 1 2 3 | 1 2 3\nCard 2: 4 | 5");
+        let total: BigUint = Cascade::default()
+            .copies(&cards)
+            .iter()
+            .map(|c| c.total())
+            .sum();
+        assert_eq!(total, BigUint::from(3u32));
     }
 }
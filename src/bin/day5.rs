@@ -1,6 +1,11 @@
+use std::{collections::HashMap, fmt};
+
 use adventofcode2023::read_input;
 
-struct Range {
+const DOMAIN: (i64, i64) = (i64::MIN / 2, i64::MAX / 2);
+
+#[derive(Clone)]
+pub struct Range {
     low: i64,
     high: i64,
     change: i64,
@@ -15,10 +20,29 @@ impl Range {
     }
 }
 
-struct Mapping {
+#[derive(Clone)]
+pub struct Mapping {
+    source: String,
+    destination: String,
     ranges: Vec<Range>,
 }
 
+impl fmt::Display for Mapping {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}-to-{} map:", self.source, self.destination)?;
+        for range in &self.ranges {
+            writeln!(
+                f,
+                "{} {} {}",
+                range.low + range.change,
+                range.low,
+                range.high - range.low
+            )?;
+        }
+        Ok(())
+    }
+}
+
 impl Mapping {
     fn map(&self, number: i64) -> i64 {
         for range in &self.ranges {
@@ -28,9 +52,63 @@ impl Mapping {
         }
         number
     }
+
+    fn split_range(&self, low: i64, high: i64) -> Vec<(i64, i64, i64)> {
+        if low >= high {
+            return vec![];
+        }
+
+        let mut result = vec![];
+        let mut pending = vec![(low, high)];
+        for range in &self.ranges {
+            let mut next = vec![];
+            for (low, high) in pending {
+                let overlap_low = low.max(range.low);
+                let overlap_high = high.min(range.high);
+                if overlap_low >= overlap_high {
+                    next.push((low, high));
+                    continue;
+                }
+                result.push((overlap_low, overlap_high, range.change));
+                if low < overlap_low {
+                    next.push((low, overlap_low));
+                }
+                if overlap_high < high {
+                    next.push((overlap_high, high));
+                }
+            }
+            pending = next;
+        }
+        result.extend(pending.into_iter().map(|(low, high)| (low, high, 0)));
+        result
+    }
+
+    pub fn preimage(&self, low: i64, high: i64) -> Vec<(i64, i64)> {
+        self.split_range(DOMAIN.0, DOMAIN.1)
+            .into_iter()
+            .filter_map(|(source_low, source_high, change)| {
+                let overlap_low = low.max(source_low + change);
+                let overlap_high = high.min(source_high + change);
+                (overlap_low < overlap_high)
+                    .then_some((overlap_low - change, overlap_high - change))
+            })
+            .collect()
+    }
+
+    pub fn overlaps(&self) -> Vec<(usize, usize)> {
+        let mut result = vec![];
+        for (i, a) in self.ranges.iter().enumerate() {
+            for (j, b) in self.ranges.iter().enumerate().skip(i + 1) {
+                if a.low.max(b.low) < a.high.min(b.high) {
+                    result.push((i, j));
+                }
+            }
+        }
+        result
+    }
 }
 
-struct Mappings {
+pub struct Mappings {
     mappings: Vec<Mapping>,
 }
 
@@ -41,12 +119,133 @@ impl Mappings {
         }
         number
     }
+
+    fn map_range(&self, low: i64, high: i64) -> Vec<(i64, i64, i64)> {
+        let mut intervals = vec![(low, high, 0)];
+        for mapping in &self.mappings {
+            intervals = intervals
+                .into_iter()
+                .flat_map(|(low, high, shift)| {
+                    mapping
+                        .split_range(low, high)
+                        .into_iter()
+                        .map(move |(low, high, change)| {
+                            (low + change, high + change, shift + change)
+                        })
+                })
+                .collect();
+        }
+        intervals
+    }
+
+    fn lowest(&self, ranges: &[(i64, i64)]) -> Option<(i64, i64)> {
+        ranges
+            .iter()
+            .flat_map(|&(low, high)| self.map_range(low, high))
+            .map(|(low, _, shift)| (low, low - shift))
+            .min()
+    }
+
+    pub fn compose(&self) -> Mapping {
+        let mut ranges = self
+            .map_range(DOMAIN.0, DOMAIN.1)
+            .into_iter()
+            .filter(|(_, _, shift)| *shift != 0)
+            .map(|(low, high, shift)| Range {
+                low: low - shift,
+                high: high - shift,
+                change: shift,
+            })
+            .collect::<Vec<_>>();
+        ranges.sort_by_key(|range| range.low);
+        Mapping {
+            source: self.mappings[0].source.clone(),
+            destination: self.mappings[self.mappings.len() - 1].destination.clone(),
+            ranges,
+        }
+    }
+
+    pub fn preimage(&self, low: i64, high: i64) -> Vec<(i64, i64)> {
+        let mut intervals = vec![(low, high)];
+        for mapping in self.mappings.iter().rev() {
+            intervals = intervals
+                .into_iter()
+                .flat_map(|(low, high)| mapping.preimage(low, high))
+                .collect();
+        }
+        intervals.sort();
+        intervals
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for (layer, mapping) in self.mappings.iter().enumerate() {
+            if let Some((i, j)) = mapping.overlaps().first() {
+                return Err(format!(
+                    "layer {} ({}-to-{}): ranges {} and {} overlap",
+                    layer, mapping.source, mapping.destination, i, j
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct Almanac {
+    mappings: Vec<Mapping>,
+}
+
+impl Almanac {
+    fn paths(&self, from: &str, to: &str, visited: &mut Vec<String>) -> Vec<Vec<usize>> {
+        if from == to {
+            return vec![vec![]];
+        }
+        visited.push(from.to_string());
+        let mut result = vec![];
+        for (i, mapping) in self.mappings.iter().enumerate() {
+            if mapping.source != from || visited.contains(&mapping.destination) {
+                continue;
+            }
+            for mut path in self.paths(&mapping.destination, to, visited) {
+                path.insert(0, i);
+                result.push(path);
+            }
+        }
+        visited.pop();
+        result
+    }
+
+    pub fn route(&self, from: &str, to: &str) -> Result<Mappings, String> {
+        let mut paths = self.paths(from, to, &mut vec![]);
+        match paths.len() {
+            0 => Err(format!("no route from {} to {}", from, to)),
+            1 if from == to => Err(format!("empty route from {} to {}", from, to)),
+            1 => Ok(Mappings {
+                mappings: paths
+                    .pop()
+                    .unwrap()
+                    .into_iter()
+                    .map(|i| self.mappings[i].clone())
+                    .collect(),
+            }),
+            n => Err(format!("{} routes from {} to {}", n, from, to)),
+        }
+    }
+
+    pub fn categories(&self) -> HashMap<&str, Vec<&str>> {
+        let mut result: HashMap<&str, Vec<&str>> = HashMap::new();
+        for mapping in &self.mappings {
+            result
+                .entry(&mapping.source)
+                .or_default()
+                .push(&mapping.destination);
+        }
+        result
+    }
 }
 
-fn parse(input: &str) -> (Mappings, Vec<i64>) {
+fn parse(input: &str) -> (Almanac, Vec<i64>) {
     let mut seeds: Vec<i64> = vec![];
-    let mut mappings = vec![];
-    let mut mapping = Mapping { ranges: vec![] };
+    let mut mappings: Vec<Mapping> = vec![];
     for line in input.lines() {
         let line = line.trim();
         if seeds.is_empty() {
@@ -54,17 +253,19 @@ fn parse(input: &str) -> (Mappings, Vec<i64>) {
                 .split(' ')
                 .map(|x| x.parse().unwrap())
                 .collect();
-        } else if line.is_empty() {
-            if !mapping.ranges.is_empty() {
-                mappings.push(mapping);
-                mapping = Mapping { ranges: vec![] };
-            }
-        } else if line.find("map").is_none() {
+        } else if let Some(name) = line.strip_suffix(" map:") {
+            let (source, destination) = name.split_once("-to-").unwrap();
+            mappings.push(Mapping {
+                source: source.to_string(),
+                destination: destination.to_string(),
+                ranges: vec![],
+            });
+        } else if !line.is_empty() {
             let values = line
                 .split(' ')
                 .map(|x| x.parse::<i64>().unwrap())
                 .collect::<Vec<_>>();
-            mapping.ranges.push(Range {
+            mappings.last_mut().unwrap().ranges.push(Range {
                 low: values[1],
                 high: values[1] + values[2],
                 change: values[0] - values[1],
@@ -72,36 +273,33 @@ fn parse(input: &str) -> (Mappings, Vec<i64>) {
         }
     }
 
-    mappings.push(mapping);
-
-    (Mappings { mappings }, seeds)
+    (Almanac { mappings }, seeds)
 }
 
 fn part1(input: &str) -> i64 {
-    let (mappings, seeds) = parse(input);
+    let (almanac, seeds) = parse(input);
+    let mappings = almanac.route("seed", "location").unwrap();
 
     seeds.into_iter().map(|x| mappings.map(x)).min().unwrap()
 }
 
-fn part2(input: &str) -> i64 {
-    let (mappings, seeds) = parse(input);
+fn part2(input: &str) -> (i64, i64) {
+    let (almanac, seeds) = parse(input);
+    let mappings = almanac.route("seed", "location").unwrap();
 
-    let mut result = i64::MAX;
-    let mut i = 0;
-    while i < seeds.len() {
-        for j in seeds[i]..seeds[i] + seeds[i + 1] {
-            result = result.min(mappings.map(j));
-        }
-        i += 2;
-    }
-    result
+    let ranges = seeds
+        .chunks(2)
+        .map(|pair| (pair[0], pair[0] + pair[1]))
+        .collect::<Vec<_>>();
+    mappings.lowest(&ranges).unwrap()
 }
 
 fn main() {
     let input = read_input(5);
 
     println!("{}", part1(&input));
-    println!("{}", part2(&input));
+    let (location, seed) = part2(&input);
+    println!("{} (seed {})", location, seed);
 }
 
 #[cfg(test)]
@@ -189,6 +387,174 @@ mod tests {
         "
         .trim();
 
-        assert_eq!(part2(input), 46);
+        assert_eq!(part2(input), (46, 82));
+    }
+
+    #[test]
+    fn test_day5_lowest_seed() {
+        let input = "
+            seeds: 79 14 55 13
+
+            seed-to-soil map:
+            50 98 2
+            52 50 48
+
+            soil-to-fertilizer map:
+            0 15 37
+            37 52 2
+            39 0 15
+
+            fertilizer-to-water map:
+            49 53 8
+            0 11 42
+            42 0 7
+            57 7 4
+
+            water-to-light map:
+            88 18 7
+            18 25 70
+
+            light-to-temperature map:
+            45 77 23
+            81 45 19
+            68 64 13
+
+            temperature-to-humidity map:
+            0 69 1
+            1 0 69
+
+            humidity-to-location map:
+            60 56 37
+            56 93 4
+        "
+        .trim();
+
+        let (almanac, _) = parse(input);
+        let mappings = almanac.route("seed", "location").unwrap();
+        assert_eq!(mappings.lowest(&[(79, 93), (55, 68)]), Some((46, 82)));
+        assert_eq!(mappings.map(82), 46);
+        assert_eq!(mappings.lowest(&[(10, 10)]), None);
+        assert_eq!(mappings.lowest(&[(10, 10), (79, 93)]), Some((46, 82)));
+
+        let total: i64 = mappings
+            .map_range(0, 100)
+            .iter()
+            .map(|(low, high, _)| high - low)
+            .sum();
+        assert_eq!(total, 100);
+    }
+
+    #[test]
+    fn test_day5_compose_and_invert() {
+        let input = "
+            seeds: 79 14 55 13
+
+            seed-to-soil map:
+            50 98 2
+            52 50 48
+
+            soil-to-fertilizer map:
+            0 15 37
+            37 52 2
+            39 0 15
+
+            fertilizer-to-water map:
+            49 53 8
+            0 11 42
+            42 0 7
+            57 7 4
+
+            water-to-light map:
+            88 18 7
+            18 25 70
+
+            light-to-temperature map:
+            45 77 23
+            81 45 19
+            68 64 13
+
+            temperature-to-humidity map:
+            0 69 1
+            1 0 69
+
+            humidity-to-location map:
+            60 56 37
+            56 93 4
+        "
+        .trim();
+
+        let (almanac, _) = parse(input);
+        let mappings = almanac.route("seed", "location").unwrap();
+        assert!(mappings.validate().is_ok());
+
+        let composed = mappings.compose();
+        for seed in -5..120 {
+            assert_eq!(composed.map(seed), mappings.map(seed));
+        }
+        let text = composed.to_string();
+        assert_eq!(text.lines().next(), Some("seed-to-location map:"));
+        assert_eq!(text.lines().count(), composed.ranges.len() + 1);
+
+        assert_eq!(mappings.preimage(46, 47), vec![(82, 83)]);
+        for (low, high) in mappings.preimage(0, 30) {
+            for seed in low..high {
+                assert!(mappings.map(seed) < 30);
+            }
+        }
+
+        let (almanac, _) = parse("seeds: 1\n\na-to-b map:\n0 5 10\n20 10 3");
+        let mappings = almanac.route("a", "b").unwrap();
+        assert_eq!(
+            mappings.validate(),
+            Err("layer 0 (a-to-b): ranges 0 and 1 overlap".to_string())
+        );
+        let mut seeds = mappings.preimage(20, 23);
+        seeds.sort();
+        assert_eq!(seeds, vec![(20, 23)]);
+        for (low, high) in seeds {
+            for seed in low..high {
+                assert!((20..23).contains(&mappings.map(seed)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_day5_routes() {
+        let input = "
+            seeds: 79 14 55 13
+
+            soil-to-fertilizer map:
+            0 15 37
+            37 52 2
+            39 0 15
+
+            seed-to-soil map:
+            50 98 2
+            52 50 48
+
+            fertilizer-to-water map:
+            49 53 8
+            0 11 42
+            42 0 7
+            57 7 4
+
+            seed-to-water map:
+            0 0 1
+        "
+        .trim();
+
+        let (almanac, _) = parse(input);
+        let mappings = almanac.route("soil", "water").unwrap();
+        assert_eq!(mappings.map(14), 49);
+        assert_eq!(almanac.route("seed", "soil").unwrap().map(79), 81);
+        assert_eq!(
+            almanac.route("water", "seed").err(),
+            Some("no route from water to seed".to_string())
+        );
+        assert_eq!(
+            almanac.route("seed", "water").err(),
+            Some("2 routes from seed to water".to_string())
+        );
+        assert_eq!(almanac.categories()["seed"], vec!["soil", "water"]);
     }
 }
@@ -1,45 +1,259 @@
 use adventofcode2023::read_input;
 
-fn parse(input: &str, remove_spaces: bool) -> Vec<Vec<i64>> {
+use num_bigint::BigInt;
+
+fn parse(input: &str, remove_spaces: bool) -> Vec<Vec<BigInt>> {
     let mut result = vec![];
     for line in input.lines() {
         let mut line = line.trim();
         line = line.split(':').collect::<Vec<_>>()[1];
         let replacement = if remove_spaces { "" } else { " " };
         result.push(
-            line.replace(" ", replacement)
+            line.replace(' ', replacement)
                 .split_whitespace()
-                .map(|x| x.parse::<i64>().unwrap())
+                .map(|x| x.parse::<BigInt>().unwrap())
                 .collect(),
         );
     }
     result
 }
 
-fn process(input: &str, remove_spaces: bool) -> i64 {
-    let mut result = 1;
-    let values = parse(input, remove_spaces);
-    for i in 0..values[0].len() {
-        let n = values[0][i];
-        let m = values[1][i];
-        let d = ((n * n - 4 * m) as f64).sqrt() as i64;
-        let k = (n - d) / 2;
-        for j in k..n {
-            if j * n - j * j > m {
-                result *= n - 2 * (j - 1) - 1;
-                break;
-            }
+fn isqrt(n: i128) -> i128 {
+    if n < 2 {
+        return n;
+    }
+    let mut x = n;
+    let mut y = (x + 1) / 2;
+    while y < x {
+        x = y;
+        y = (x + n / x) / 2;
+    }
+    x
+}
+
+fn interval(n: i128, m: i128) -> Option<(i128, i128)> {
+    let d = n * n - 4 * m;
+    if d < 0 {
+        return None;
+    }
+    let mut low = (n - isqrt(d)) / 2;
+    while low <= n - low && low * (n - low) <= m {
+        low += 1;
+    }
+    if low > n - low {
+        return None;
+    }
+    Some((low, n - low))
+}
+
+fn interval_big(n: &BigInt, m: &BigInt) -> Option<(BigInt, BigInt)> {
+    let d: BigInt = n * n - 4 * m;
+    if d < BigInt::ZERO {
+        return None;
+    }
+    let mut low: BigInt = (n - d.sqrt()) / 2;
+    while low <= n - &low && &low * (n - &low) <= *m {
+        low += 1;
+    }
+    if low > n - &low {
+        return None;
+    }
+    let high = n - &low;
+    Some((low, high))
+}
+
+pub fn winning_interval(time: &BigInt, distance: &BigInt) -> Option<(BigInt, BigInt)> {
+    match (i64::try_from(time), i64::try_from(distance)) {
+        (Ok(n), Ok(m)) => interval(n as i128, m as i128)
+            .map(|(low, high)| (BigInt::from(low), BigInt::from(high))),
+        _ => interval_big(time, distance),
+    }
+}
+
+pub trait RaceModel {
+    fn distance(&self, race: usize, hold: i64, time: i64) -> f64;
+
+    fn validate(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn winning_count(&self, race: usize, time: &BigInt, record: &BigInt) -> Result<BigInt, String> {
+        self.validate()?;
+        search(self, race, time, record)
+    }
+}
+
+fn search<M: RaceModel + ?Sized>(
+    model: &M,
+    race: usize,
+    time: &BigInt,
+    record: &BigInt,
+) -> Result<BigInt, String> {
+    let time = i64::try_from(time)
+        .map_err(|_| format!("race {}: time {} is too large to search", race, time))?;
+    let record = i64::try_from(record)
+        .map_err(|_| format!("race {}: record {} is too large to search", race, record))?
+        as f64;
+    let beats = |hold: i64| model.distance(race, hold, time) > record;
+
+    // The search assumes distance is unimodal in the hold time.
+    let (mut low, mut high) = (0, time);
+    while high - low > 2 {
+        let left = low + (high - low) / 3;
+        let right = high - (high - low) / 3;
+        if model.distance(race, left, time) < model.distance(race, right, time) {
+            low = left + 1;
+        } else {
+            high = right - 1;
         }
     }
-    result
+    let peak = (low..=high)
+        .max_by(|a, b| {
+            model
+                .distance(race, *a, time)
+                .total_cmp(&model.distance(race, *b, time))
+        })
+        .unwrap();
+    if !model.distance(race, peak, time).is_finite() {
+        return Err(format!("race {}: distance is not finite", race));
+    }
+    if !beats(peak) {
+        return Ok(BigInt::ZERO);
+    }
+
+    let (mut low, mut high) = (0, peak);
+    while low < high {
+        let middle = (low + high) / 2;
+        if beats(middle) {
+            high = middle;
+        } else {
+            low = middle + 1;
+        }
+    }
+    let first = low;
+
+    let (mut low, mut high) = (peak, time);
+    while low < high {
+        let middle = (low + high + 1) / 2;
+        if beats(middle) {
+            low = middle;
+        } else {
+            high = middle - 1;
+        }
+    }
+    Ok(BigInt::from(low - first + 1))
+}
+
+pub struct Linear {
+    pub acceleration: i64,
+}
+
+impl RaceModel for Linear {
+    fn distance(&self, _: usize, hold: i64, time: i64) -> f64 {
+        self.acceleration as f64 * hold as f64 * (time - hold) as f64
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.acceleration <= 0 {
+            return Err(format!(
+                "acceleration must be positive, got {}",
+                self.acceleration
+            ));
+        }
+        Ok(())
+    }
+
+    fn winning_count(&self, _: usize, time: &BigInt, record: &BigInt) -> Result<BigInt, String> {
+        self.validate()?;
+        Ok(
+            match winning_interval(time, &(record / self.acceleration)) {
+                Some((low, high)) => high - low + 1,
+                None => BigInt::ZERO,
+            },
+        )
+    }
 }
 
-fn part1(input: &str) -> i64 {
-    process(input, false)
+pub struct Capped {
+    pub acceleration: i64,
+    pub top_speed: i64,
+}
+
+impl RaceModel for Capped {
+    fn distance(&self, _: usize, hold: i64, time: i64) -> f64 {
+        let speed = (self.acceleration as f64 * hold as f64).min(self.top_speed as f64);
+        speed * (time - hold) as f64
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.acceleration <= 0 || self.top_speed <= 0 {
+            return Err(format!(
+                "acceleration and top speed must be positive, got {} and {}",
+                self.acceleration, self.top_speed
+            ));
+        }
+        Ok(())
+    }
 }
 
-fn part2(input: &str) -> i64 {
-    process(input, true)
+pub struct Decaying {
+    pub rate: f64,
+    pub decay: f64,
+}
+
+impl RaceModel for Decaying {
+    fn validate(&self) -> Result<(), String> {
+        if !self.rate.is_finite() || !self.decay.is_finite() || self.decay == 1.0 {
+            return Err(format!(
+                "rate and decay must be finite and decay must not be 1, got {} and {}",
+                self.rate, self.decay
+            ));
+        }
+        Ok(())
+    }
+
+    fn distance(&self, _: usize, hold: i64, time: i64) -> f64 {
+        let speed = self.rate * (1.0 - self.decay.powi(hold as i32)) / (1.0 - self.decay);
+        speed * (time - hold) as f64
+    }
+}
+
+pub struct Polynomial {
+    pub coefficients: Vec<Vec<f64>>,
+}
+
+impl RaceModel for Polynomial {
+    fn distance(&self, race: usize, hold: i64, time: i64) -> f64 {
+        let speed = self.coefficients[race]
+            .iter()
+            .rev()
+            .fold(0.0, |acc, coefficient| acc * hold as f64 + coefficient);
+        speed * (time - hold) as f64
+    }
+
+    fn winning_count(&self, race: usize, time: &BigInt, record: &BigInt) -> Result<BigInt, String> {
+        if self.coefficients.get(race).is_none() {
+            return Err(format!("race {}: no polynomial", race));
+        }
+        search(self, race, time, record)
+    }
+}
+
+fn process(input: &str, remove_spaces: bool, model: &dyn RaceModel) -> Result<BigInt, String> {
+    let mut result = BigInt::from(1);
+    let values = parse(input, remove_spaces);
+    for (race, (time, distance)) in values[0].iter().zip(&values[1]).enumerate() {
+        result *= model.winning_count(race, time, distance)?;
+    }
+    Ok(result)
+}
+
+fn part1(input: &str) -> BigInt {
+    process(input, false, &Linear { acceleration: 1 }).unwrap()
+}
+
+fn part2(input: &str) -> BigInt {
+    process(input, true, &Linear { acceleration: 1 }).unwrap()
 }
 
 fn main() {
@@ -61,7 +275,7 @@ mod tests {
         "
         .trim();
 
-        assert_eq!(part1(input), 288);
+        assert_eq!(part1(input), BigInt::from(288));
     }
 
     #[test]
@@ -72,6 +286,143 @@ mod tests {
         "
         .trim();
 
-        assert_eq!(part2(input), 71503);
+        assert_eq!(part2(input), BigInt::from(71503));
+    }
+
+    #[test]
+    fn test_day6_exact_intervals() {
+        let interval = |time: &str, distance: &str| {
+            winning_interval(&time.parse().unwrap(), &distance.parse().unwrap())
+                .map(|(low, high)| (low.to_string(), high.to_string()))
+        };
+        let pair = |low: &str, high: &str| Some((low.to_string(), high.to_string()));
+
+        assert_eq!(interval("30", "200"), pair("11", "19"));
+        assert_eq!(interval("4", "4"), None);
+        assert_eq!(interval("4", "3"), pair("2", "2"));
+
+        let time = 3_037_000_500i128 * 2;
+        let distance = (time / 2) * (time / 2) - 1;
+        assert_eq!(
+            interval(&time.to_string(), &distance.to_string()),
+            pair("3037000500", "3037000500")
+        );
+
+        let time = "123456789012345678901234567890";
+        let half = "61728394506172839450617283945";
+        let distance = "3810394688309709187623837890634049696875476299968754763024";
+        assert_eq!(interval(time, distance), pair(half, half));
+    }
+
+    #[test]
+    fn test_day6_race_models() {
+        let input = "
+            Time:      7  15   30
+            Distance:  9  40  200
+        "
+        .trim();
+
+        let linear = Linear { acceleration: 2 };
+        assert_eq!(
+            process(input, false, &linear),
+            Ok(BigInt::from(6 * 12 * 23))
+        );
+
+        struct Numeric(Linear);
+        impl RaceModel for Numeric {
+            fn distance(&self, race: usize, hold: i64, time: i64) -> f64 {
+                self.0.distance(race, hold, time)
+            }
+        }
+        let numeric = Numeric(Linear { acceleration: 1 });
+        assert_eq!(process(input, false, &numeric), Ok(BigInt::from(288)));
+        assert_eq!(process(input, true, &numeric), Ok(BigInt::from(71503)));
+
+        let capped = Capped {
+            acceleration: 1,
+            top_speed: 5,
+        };
+        assert_eq!(process(input, false, &capped), Ok(BigInt::ZERO));
+        assert_eq!(
+            capped.winning_count(0, &BigInt::from(30), &BigInt::from(100)),
+            Ok(BigInt::from(6))
+        );
+
+        let decaying = Decaying {
+            rate: 1.0,
+            decay: 0.5,
+        };
+        assert_eq!(
+            decaying.winning_count(0, &BigInt::from(10), &BigInt::from(7)),
+            Ok(BigInt::from(6))
+        );
+
+        let polynomial = Polynomial {
+            coefficients: vec![vec![0.0, 1.0], vec![0.0, 0.0, 1.0], vec![3.0]],
+        };
+        assert_eq!(process(input, false, &polynomial), Ok(BigInt::ZERO));
+        assert_eq!(
+            polynomial.winning_count(1, &BigInt::from(15), &BigInt::from(40)),
+            Ok(BigInt::from(13))
+        );
+
+        assert_eq!(
+            process(input, false, &Linear { acceleration: 0 }),
+            Err("acceleration must be positive, got 0".to_string())
+        );
+        let stalled = Decaying {
+            rate: 1.0,
+            decay: 1.0,
+        };
+        assert_eq!(
+            stalled.winning_count(0, &BigInt::from(10), &BigInt::from(7)),
+            Err("rate and decay must be finite and decay must not be 1, got 1 and 1".to_string())
+        );
+        let huge = "123456789012345678901234567890".parse::<BigInt>().unwrap();
+        assert_eq!(
+            capped.winning_count(2, &huge, &BigInt::from(7)),
+            Err("race 2: time 123456789012345678901234567890 is too large to search".to_string())
+        );
+        let overflowing = Polynomial {
+            coefficients: vec![vec![f64::MAX, f64::MAX]],
+        };
+        assert_eq!(
+            overflowing.winning_count(0, &BigInt::from(10), &BigInt::from(7)),
+            Err("race 0: distance is not finite".to_string())
+        );
+        let short = Polynomial {
+            coefficients: vec![vec![0.0, 1.0]],
+        };
+        assert_eq!(
+            process(input, false, &short),
+            Err("race 1: no polynomial".to_string())
+        );
+
+        assert_eq!(
+            process(
+                input,
+                false,
+                &Capped {
+                    acceleration: 1,
+                    top_speed: 0,
+                }
+            ),
+            Err("acceleration and top speed must be positive, got 1 and 0".to_string())
+        );
+        let fast = Capped {
+            acceleration: i64::MAX,
+            top_speed: i64::MAX,
+        };
+        assert_eq!(
+            fast.winning_count(0, &BigInt::from(10), &BigInt::from(7)),
+            Ok(BigInt::from(9))
+        );
+        assert!(
+            Linear {
+                acceleration: i64::MAX
+            }
+            .distance(0, 5, 10)
+                > 0.0
+        );
     }
 }
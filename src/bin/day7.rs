@@ -2,74 +2,208 @@ use std::{cmp::Ordering, collections::HashMap};
 
 use adventofcode2023::read_input;
 
-struct HandsManager {
-    cards_order: &'static str,
-    cards_map: HashMap<char, usize>,
+pub struct Category {
+    pub name: &'static str,
+    pub groups: Vec<usize>,
+    pub flush: bool,
+    pub straight: bool,
+}
+
+impl Category {
+    pub fn groups(name: &'static str, groups: &[usize]) -> Self {
+        Self {
+            name,
+            groups: groups.to_vec(),
+            flush: false,
+            straight: false,
+        }
+    }
+}
+
+pub struct HandsManager {
+    cards_order: String,
+    cards_map: HashMap<char, u64>,
+    rank_bits: usize,
+    suits: Option<String>,
+    wildcards: Vec<char>,
+    hand_size: usize,
+    categories: Vec<Category>,
 }
 
 impl HandsManager {
-    fn new(cards_order: &'static str) -> Self {
+    pub fn new(
+        cards_order: &str,
+        suits: Option<&str>,
+        wildcards: &[char],
+        hand_size: usize,
+        categories: Vec<Category>,
+    ) -> Self {
         let mut cards_map = HashMap::new();
         for (i, card) in cards_order.chars().enumerate() {
-            cards_map.insert(card, i);
+            cards_map.insert(card, i as u64);
         }
+        let rank_bits = (usize::BITS - (cards_order.len() - 1).leading_zeros()) as usize;
+        let category_bits = (usize::BITS - categories.len().leading_zeros()) as usize;
+        assert!(
+            rank_bits * hand_size + category_bits <= 64,
+            "hand does not fit into a u64 key"
+        );
         Self {
-            cards_order,
+            cards_order: cards_order.to_string(),
             cards_map,
+            rank_bits,
+            suits: suits.map(|suits| suits.to_string()),
+            wildcards: wildcards.to_vec(),
+            hand_size,
+            categories,
         }
     }
 
-    fn stats(&self, hand: &str) -> [i32; 5] {
-        let mut cards = vec![0; self.cards_map.len()];
-        for card in hand.chars() {
-            cards[self.cards_map[&card]] += 1;
-        }
-        let mut stats = [0; 5];
-        let mut jokers = 0;
-        if self.cards_order.chars().next().unwrap() == 'J' {
-            jokers = cards[0];
-            if jokers == 5 {
-                stats[4] = 1;
-                return stats;
+    pub fn camel(cards_order: &str, wildcards: &[char]) -> Self {
+        let categories = vec![
+            Category::groups("high card", &[1, 1, 1, 1, 1]),
+            Category::groups("one pair", &[2, 1, 1, 1]),
+            Category::groups("two pair", &[2, 2, 1]),
+            Category::groups("three of a kind", &[3, 1, 1]),
+            Category::groups("full house", &[3, 2]),
+            Category::groups("four of a kind", &[4, 1]),
+            Category::groups("five of a kind", &[5]),
+        ];
+        Self::new(cards_order, None, wildcards, 5, categories)
+    }
+
+    fn cards(&self, hand: &str) -> Vec<(char, Option<char>)> {
+        let chars = hand.chars().collect::<Vec<_>>();
+        let cards = match self.suits {
+            Some(_) => chars
+                .chunks(2)
+                .map(|card| (card[0], Some(card[1])))
+                .collect(),
+            None => chars
+                .into_iter()
+                .map(|card| (card, None))
+                .collect::<Vec<_>>(),
+        };
+        assert_eq!(cards.len(), self.hand_size, "wrong hand size: {}", hand);
+        cards
+    }
+
+    fn matches(&self, category: &Category, cards: &[(char, Option<char>)]) -> bool {
+        let natural = cards
+            .iter()
+            .filter(|(rank, _)| !self.wildcards.contains(rank))
+            .collect::<Vec<_>>();
+
+        if category.flush {
+            let mut suits = natural.iter().map(|(_, suit)| suit);
+            if let Some(first) = suits.next() {
+                if first.is_none() || suits.any(|suit| suit != first) {
+                    return false;
+                }
             }
-            cards[0] = 0;
         }
-        for card in cards {
-            if card > 0 {
-                stats[card - 1] += 1;
+
+        if category.straight {
+            let order = self
+                .cards_order
+                .chars()
+                .filter(|card| !self.wildcards.contains(card))
+                .collect::<String>();
+            let mut ranks = natural
+                .iter()
+                .map(|(rank, _)| order.find(*rank).unwrap())
+                .collect::<Vec<_>>();
+            ranks.sort();
+            ranks.dedup();
+            if ranks.len() != natural.len() || order.len() < self.hand_size {
+                return false;
+            }
+            if let (Some(low), Some(high)) = (ranks.first(), ranks.last()) {
+                if high - low >= self.hand_size {
+                    return false;
+                }
             }
         }
-        for i in (0..stats.len()).rev() {
-            if stats[i] > 0 {
-                stats[i] -= 1;
-                stats[i + jokers] += 1;
-                break;
+
+        if !category.groups.is_empty() {
+            let mut counts = HashMap::new();
+            for (rank, _) in &natural {
+                *counts.entry(rank).or_insert(0) += 1;
+            }
+            let mut counts = counts.into_values().collect::<Vec<usize>>();
+            counts.sort_by(|a, b| b.cmp(a));
+            if counts.len() > category.groups.len()
+                || counts.iter().zip(&category.groups).any(|(c, g)| c > g)
+            {
+                return false;
             }
         }
-        stats
+
+        true
     }
-}
 
-fn compare(hands_manager: &HandsManager, first: &str, second: &str) -> Ordering {
-    let stats1 = hands_manager.stats(first);
-    let stats2 = hands_manager.stats(second);
-    for (v1, v2) in stats1.iter().rev().zip(stats2.iter().rev()) {
-        if v1 < v2 {
-            return Ordering::Less;
-        } else if v1 > v2 {
-            return Ordering::Greater;
+    pub fn category(&self, hand: &str) -> usize {
+        let cards = self.cards(hand);
+        (0..self.categories.len())
+            .rev()
+            .find(|&i| self.matches(&self.categories[i], &cards))
+            .unwrap_or_else(|| panic!("no category matches {}", hand))
+    }
+
+    pub fn key(&self, hand: &str) -> u64 {
+        let mut key = self.category(hand) as u64;
+        for (card, _) in self.cards(hand) {
+            key = (key << self.rank_bits) | self.cards_map[&card];
         }
+        key
     }
-    for (ch1, ch2) in first.chars().zip(second.chars()) {
-        let i1 = hands_manager.cards_order.find(ch1).unwrap();
-        let i2 = hands_manager.cards_order.find(ch2).unwrap();
-        if i1 < i2 {
-            return Ordering::Less;
-        } else if i1 > i2 {
-            return Ordering::Greater;
+
+    pub fn report(&self, hands: &[&str]) -> Vec<(usize, &'static str)> {
+        let keys = hands.iter().map(|hand| self.key(hand)).collect::<Vec<_>>();
+        let mut order = (0..hands.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&i| keys[i]);
+        let mut result = vec![(0, ""); hands.len()];
+        for (rank, i) in order.into_iter().enumerate() {
+            let category = (keys[i] >> (self.rank_bits * self.hand_size)) as usize;
+            result[i] = (rank + 1, self.categories[category].name);
         }
+        result
     }
-    Ordering::Equal
+
+    pub fn explain(&self, first: &str, second: &str) -> String {
+        let category1 = self.category(first);
+        let category2 = self.category(second);
+        let name1 = self.categories[category1].name;
+        let name2 = self.categories[category2].name;
+        let (winner, loser, reason) = match category1.cmp(&category2) {
+            Ordering::Greater => (first, second, format!("{} outranks {}", name1, name2)),
+            Ordering::Less => (second, first, format!("{} outranks {}", name2, name1)),
+            Ordering::Equal => {
+                let cards1 = self.cards(first);
+                let cards2 = self.cards(second);
+                let position = cards1.iter().zip(&cards2).position(|(a, b)| a.0 != b.0);
+                match position {
+                    None => return format!("{} ties {}: both {}", first, second, name1),
+                    Some(i) => {
+                        let (card1, card2) = (cards1[i].0, cards2[i].0);
+                        let reason = |high: char, low: char| {
+                            format!("both {}, {} beats {} at card {}", name1, high, low, i + 1)
+                        };
+                        if compare(self, first, second) == Ordering::Greater {
+                            (first, second, reason(card1, card2))
+                        } else {
+                            (second, first, reason(card2, card1))
+                        }
+                    }
+                }
+            }
+        };
+        format!("{} beats {}: {}", winner, loser, reason)
+    }
+}
+
+fn compare(hands_manager: &HandsManager, first: &str, second: &str) -> Ordering {
+    hands_manager.key(first).cmp(&hands_manager.key(second))
 }
 
 fn parse(input: &str) -> Vec<(String, i32)> {
@@ -82,11 +216,13 @@ fn parse(input: &str) -> Vec<(String, i32)> {
     bids
 }
 
-fn process(input: &str, cards_order: &'static str) -> i32 {
-    let mut bids = parse(input);
-    let hands_manager = HandsManager::new(cards_order);
+fn process(input: &str, hands_manager: &HandsManager) -> i32 {
+    let mut bids = parse(input)
+        .into_iter()
+        .map(|(hand, bid)| (hands_manager.key(&hand), bid))
+        .collect::<Vec<_>>();
     let mut result = 0;
-    bids.sort_by(|a, b| compare(&hands_manager, &a.0, &b.0));
+    bids.sort_unstable_by_key(|bid| bid.0);
     for (i, bid) in bids.iter().enumerate() {
         result += (i as i32 + 1) * bid.1;
     }
@@ -94,13 +230,11 @@ fn process(input: &str, cards_order: &'static str) -> i32 {
 }
 
 fn part1(input: &str) -> i32 {
-    let cards_order = "23456789TJQKA";
-    process(input, cards_order)
+    process(input, &HandsManager::camel("23456789TJQKA", &[]))
 }
 
 fn part2(input: &str) -> i32 {
-    let cards_order = "J23456789TQKA";
-    process(input, cards_order)
+    process(input, &HandsManager::camel("J23456789TQKA", &['J']))
 }
 
 fn main() {
@@ -141,4 +275,93 @@ mod tests {
 
         assert_eq!(part2(input), 5905);
     }
+
+    #[test]
+    fn test_day7_custom_rules() {
+        let jokers = HandsManager::camel("J23456789TQKA", &['J']);
+        assert_eq!(
+            jokers.explain("KTJJT", "QQQJA"),
+            "KTJJT beats QQQJA: both four of a kind, K beats Q at card 1"
+        );
+        assert_eq!(
+            jokers.explain("T55J5", "KK677"),
+            "T55J5 beats KK677: four of a kind outranks two pair"
+        );
+        assert_eq!(
+            jokers.explain("JJJJJ", "JJJJJ"),
+            "JJJJJ ties JJJJJ: both five of a kind"
+        );
+
+        let poker = HandsManager::new(
+            "23456789TJQKA",
+            Some("CDHS"),
+            &['2'],
+            5,
+            vec![
+                Category::groups("high card", &[1, 1, 1, 1, 1]),
+                Category::groups("pair", &[2, 1, 1, 1]),
+                Category {
+                    name: "straight",
+                    groups: vec![],
+                    flush: false,
+                    straight: true,
+                },
+                Category {
+                    name: "flush",
+                    groups: vec![],
+                    flush: true,
+                    straight: false,
+                },
+                Category {
+                    name: "straight flush",
+                    groups: vec![],
+                    flush: true,
+                    straight: true,
+                },
+            ],
+        );
+        assert_eq!(poker.category("3H4H5H6H7H"), 4);
+        assert_eq!(poker.category("3H4H5H6H9H"), 3);
+        assert_eq!(poker.category("3H4D5H6H7H"), 2);
+        assert_eq!(poker.category("3H4D2C6H7H"), 2);
+        assert_eq!(poker.category("3H3D8C6HKH"), 1);
+        assert_eq!(poker.category("3H9D8C6HKH"), 0);
+        assert_eq!(
+            poker.explain("3H4D2C6H7H", "3H4H5H6H9H"),
+            "3H4H5H6H9H beats 3H4D2C6H7H: flush outranks straight"
+        );
+
+        let short = HandsManager::new(
+            "ABC",
+            None,
+            &[],
+            3,
+            vec![
+                Category::groups("mixed", &[1, 1, 1]),
+                Category::groups("pair", &[2, 1]),
+                Category::groups("triple", &[3]),
+            ],
+        );
+        assert_eq!(
+            process("ABC 1\nCCA 2\nBBB 3\nAAB 4", &short),
+            1 + 8 + 6 + 12
+        );
+    }
+
+    #[test]
+    fn test_day7_report() {
+        let hands_manager = HandsManager::camel("23456789TJQKA", &[]);
+        assert_eq!(
+            hands_manager.report(&["32T3K", "T55J5", "KK677", "KTJJT", "QQQJA"]),
+            vec![
+                (1, "one pair"),
+                (4, "three of a kind"),
+                (3, "two pair"),
+                (2, "two pair"),
+                (5, "three of a kind"),
+            ]
+        );
+        assert!(hands_manager.key("AAAAA") > hands_manager.key("KKKKK"));
+        assert!(hands_manager.key("22223") > hands_manager.key("AAAKK"));
+    }
 }
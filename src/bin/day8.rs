@@ -1,109 +1,289 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, env};
 
 use adventofcode2023::read_input;
 
-fn parse(input: &str) -> (String, HashMap<String, Vec<String>>) {
-    let mut mapping = HashMap::new();
+pub struct Network {
+    pub names: Vec<String>,
+    pub indices: HashMap<String, usize>,
+    pub successors: Vec<[usize; 2]>,
+}
+
+impl Network {
+    fn intern(&mut self, name: &str) -> usize {
+        if let Some(&index) = self.indices.get(name) {
+            return index;
+        }
+        self.names.push(name.to_string());
+        self.indices.insert(name.to_string(), self.names.len() - 1);
+        self.successors.push([0, 0]);
+        self.names.len() - 1
+    }
+
+    pub fn index(&self, name: &str) -> usize {
+        self.indices[name]
+    }
+
+    pub fn select(&self, predicate: impl Fn(&str) -> bool) -> Vec<usize> {
+        (0..self.names.len())
+            .filter(|&i| predicate(&self.names[i]))
+            .collect()
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut result = String::from("digraph network {\n");
+        for (i, name) in self.names.iter().enumerate() {
+            let [left, right] = self.successors[i];
+            if left == right {
+                result += &format!(
+                    "    \"{}\" -> \"{}\" [label=\"LR\"];\n",
+                    name, self.names[left]
+                );
+            } else {
+                result += &format!(
+                    "    \"{}\" -> \"{}\" [label=\"L\"];\n",
+                    name, self.names[left]
+                );
+                result += &format!(
+                    "    \"{}\" -> \"{}\" [label=\"R\"];\n",
+                    name, self.names[right]
+                );
+            }
+        }
+        result += "}\n";
+        result
+    }
+
+    pub fn path(
+        &self,
+        instructions: &[usize],
+        start: usize,
+        goal: impl Fn(&str) -> bool,
+    ) -> Option<Vec<&str>> {
+        let mut seen = vec![vec![false; instructions.len()]; self.names.len()];
+        let mut path = vec![self.names[start].as_str()];
+        let mut position = start;
+        for (steps, instruction) in instructions.iter().cycle().enumerate() {
+            let index = steps % instructions.len();
+            if seen[position][index] {
+                return None;
+            }
+            seen[position][index] = true;
+            position = self.successors[position][*instruction];
+            path.push(&self.names[position]);
+            if goal(&self.names[position]) {
+                return Some(path);
+            }
+        }
+        None
+    }
+}
+
+pub fn matches(pattern: &str, name: &str) -> bool {
+    match (pattern.chars().next(), name.chars().next()) {
+        (None, None) => true,
+        (Some('*'), n) => {
+            matches(&pattern[1..], name)
+                || n.is_some_and(|n| matches(pattern, &name[n.len_utf8()..]))
+        }
+        (Some(p), Some(n)) if p == '?' || p == n => {
+            matches(&pattern[p.len_utf8()..], &name[n.len_utf8()..])
+        }
+        _ => false,
+    }
+}
+
+fn parse(input: &str) -> (Vec<usize>, Network) {
+    let mut network = Network {
+        names: vec![],
+        indices: HashMap::new(),
+        successors: vec![],
+    };
     let mut iter = input.lines();
-    let instructions = iter.next().unwrap().to_string();
+    let instructions = iter
+        .next()
+        .unwrap()
+        .trim()
+        .chars()
+        .map(|ch| if ch == 'L' { 0 } else { 1 })
+        .collect();
     iter.next();
     for line in iter {
         let line = line.trim();
         let parts = line.split(" = ").collect::<Vec<_>>();
         let targets = parts[1].split(", ").collect::<Vec<_>>();
-        mapping.insert(
-            parts[0].to_string(),
-            vec![
-                targets[0].replace("(", "").to_string(),
-                targets[1].replace(")", "").to_string(),
-            ],
-        );
+        let node = network.intern(parts[0]);
+        let left = network.intern(&targets[0].replace('(', ""));
+        let right = network.intern(&targets[1].replace(')', ""));
+        network.successors[node] = [left, right];
     }
-    (instructions, mapping)
+    (instructions, network)
 }
 
-fn gcd(mut a: usize, mut b: usize) -> usize {
-    if a < b {
-        let t = a;
-        a = b;
-        b = t;
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        return (a, 1, 0);
     }
+    let (g, x, y) = extended_gcd(b, a % b);
+    (g, y, x - (a / b) * y)
+}
 
-    loop {
-        a = a % b;
-        if a == 0 {
-            return b;
-        }
-        let t = a;
-        a = b;
-        b = t;
+fn crt(first: (i128, i128), second: (i128, i128)) -> Option<(i128, i128)> {
+    let (r1, m1) = first;
+    let (r2, m2) = second;
+    let (g, x, _) = extended_gcd(m1, m2);
+    if (r2 - r1) % g != 0 {
+        return None;
     }
+    let modulus = m1 / g * m2;
+    let k = ((r2 - r1) / g % (m2 / g) * x % (m2 / g) + m2 / g) % (m2 / g);
+    Some(((r1 + m1 * k) % modulus, modulus))
 }
 
-fn lcm(numbers: Vec<usize>) -> usize {
-    let mut current_gcd = numbers[0];
-    let mut result = numbers[0];
-    for i in 1..numbers.len() {
-        current_gcd = gcd(current_gcd, numbers[i]);
-        result *= numbers[i];
-        result /= current_gcd;
-    }
-    result
+pub struct Cycle {
+    pub start: usize,
+    pub period: usize,
+    pub prefix_hits: Vec<usize>,
+    pub offsets: Vec<usize>,
 }
 
-fn part1(instructions: &str, mapping: &HashMap<String, Vec<String>>) -> usize {
-    let mut position = "AAA";
-    let mut steps = 1;
-    loop {
-        for ch in instructions.chars() {
-            position = &mapping[position][if ch == 'L' { 0 } else { 1 }];
-            if position == "ZZZ" {
-                return steps;
+impl Cycle {
+    pub fn detect(
+        instructions: &[usize],
+        network: &Network,
+        start: usize,
+        goal: impl Fn(&str) -> bool,
+    ) -> Self {
+        let goals = network
+            .names
+            .iter()
+            .map(|name| goal(name))
+            .collect::<Vec<_>>();
+        let mut seen = vec![vec![None; instructions.len()]; network.names.len()];
+        let mut hits = vec![];
+        let mut position = start;
+        let mut steps = 0;
+        loop {
+            let index = steps % instructions.len();
+            if let Some(first) = seen[position][index] {
+                let (prefix_hits, offsets) = hits.into_iter().partition(|&hit| hit < first);
+                return Self {
+                    start: first,
+                    period: steps - first,
+                    prefix_hits,
+                    offsets,
+                };
             }
-
+            seen[position][index] = Some(steps);
+            if goals[position] {
+                hits.push(steps);
+            }
+            position = network.successors[position][instructions[index]];
             steps += 1;
         }
     }
-}
 
-fn part2(instructions: &str, mapping: &HashMap<String, Vec<String>>) -> usize {
-    let mut positions = vec![];
-    for position in mapping.keys() {
-        if position.chars().last().unwrap() == 'A' {
-            positions.push(position);
+    pub fn hits(&self, steps: usize) -> bool {
+        if steps < self.start {
+            self.prefix_hits.contains(&steps)
+        } else {
+            let offset = self.start + (steps - self.start) % self.period;
+            self.offsets.contains(&offset)
         }
     }
+}
 
-    let mut steps = 1;
-    let mut first_values = vec![0; positions.len()];
-    let mut values_set = 0;
-    loop {
-        for ch in instructions.chars() {
-            for i in 0..positions.len() {
-                let position = &mapping[positions[i]][if ch == 'L' { 0 } else { 1 }];
-                positions[i] = position;
-                if position.chars().last().unwrap() == 'Z' {
-                    if first_values[i] == 0 {
-                        first_values[i] = steps;
-                        values_set += 1;
-                        if values_set == positions.len() {
-                            return lcm(first_values);
-                        }
+pub fn earliest(cycles: &[Cycle]) -> Option<u128> {
+    if cycles.is_empty() {
+        return None;
+    }
+
+    let periodic_from = cycles.iter().map(|cycle| cycle.start).max().unwrap_or(0);
+    if let Some(steps) = (1..periodic_from).find(|&steps| cycles.iter().all(|c| c.hits(steps))) {
+        return Some(steps as u128);
+    }
+
+    let mut solutions = vec![(0, 1)];
+    for cycle in cycles {
+        let mut next = vec![];
+        for &solution in &solutions {
+            for &offset in &cycle.offsets {
+                let congruence = (offset as i128 % cycle.period as i128, cycle.period as i128);
+                if let Some(merged) = crt(solution, congruence) {
+                    if !next.contains(&merged) {
+                        next.push(merged);
                     }
                 }
             }
-
-            steps += 1;
         }
+        solutions = next;
     }
+
+    let lower = periodic_from.max(1) as i128;
+    solutions
+        .into_iter()
+        .map(|(residue, modulus)| {
+            let k = (lower - residue + modulus - 1).div_euclid(modulus);
+            (residue + k.max(0) * modulus) as u128
+        })
+        .min()
+}
+
+pub fn ghost_walk(
+    instructions: &[usize],
+    network: &Network,
+    start: impl Fn(&str) -> bool,
+    goal: impl Fn(&str) -> bool,
+) -> Option<u128> {
+    let cycles = network
+        .select(start)
+        .into_iter()
+        .map(|position| Cycle::detect(instructions, network, position, &goal))
+        .collect::<Vec<_>>();
+    earliest(&cycles)
+}
+
+fn part1(instructions: &[usize], network: &Network) -> usize {
+    let path = network
+        .path(instructions, network.index("AAA"), |node| node == "ZZZ")
+        .expect("ZZZ is unreachable from AAA");
+    path.len() - 1
+}
+
+fn part2(instructions: &[usize], network: &Network) -> u128 {
+    ghost_walk(
+        instructions,
+        network,
+        |node| node.ends_with('A'),
+        |node| node.ends_with('Z'),
+    )
+    .expect("ghosts never reach goal nodes at the same time")
 }
 
 fn main() {
     let input = read_input(8);
-    let (instructions, mapping) = parse(&input);
+    let (instructions, network) = parse(&input);
+
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if args.len() == 1 && args[0] == "dot" {
+        print!("{}", network.to_dot());
+        return;
+    }
+    if let [start, goal] = &args[..] {
+        let steps = ghost_walk(
+            &instructions,
+            &network,
+            |node| matches(start, node),
+            |node| matches(goal, node),
+        );
+        match steps {
+            Some(steps) => println!("{}", steps),
+            None => println!("unreachable"),
+        }
+        return;
+    }
 
-    println!("{}", part1(&instructions, &mapping));
-    println!("{}", part2(&instructions, &mapping));
+    println!("{}", part1(&instructions, &network));
+    println!("{}", part2(&instructions, &network));
 }
 
 #[cfg(test)]
@@ -125,8 +305,8 @@ mod tests {
         "
         .trim();
 
-        let (instructions, mapping) = parse(&input);
-        assert_eq!(part1(&instructions, &mapping), 2);
+        let (instructions, network) = parse(input);
+        assert_eq!(part1(&instructions, &network), 2);
 
         let input = "
             LLR
@@ -137,8 +317,8 @@ mod tests {
         "
         .trim();
 
-        let (instructions, mapping) = parse(&input);
-        assert_eq!(part1(&instructions, &mapping), 6);
+        let (instructions, network) = parse(input);
+        assert_eq!(part1(&instructions, &network), 6);
     }
 
     #[test]
@@ -157,7 +337,135 @@ mod tests {
         "
         .trim();
 
-        let (instructions, mapping) = parse(&input);
-        assert_eq!(part2(&instructions, &mapping), 6);
+        let (instructions, network) = parse(input);
+        assert_eq!(part2(&instructions, &network), 6);
+    }
+
+    #[test]
+    fn test_day8_unaligned_cycles() {
+        let input = "
+            L
+
+            11A = (11B, 11B)
+            11B = (11Z, 11Z)
+            11Z = (11C, 11C)
+            11C = (11B, 11B)
+            22A = (22B, 22B)
+            22B = (22C, 22C)
+            22C = (22Z, 22Z)
+            22Z = (22C, 22C)
+        "
+        .trim();
+
+        let (instructions, network) = parse(input);
+        let cycle = Cycle::detect(&instructions, &network, network.index("11A"), |node| {
+            node.ends_with('Z')
+        });
+        assert_eq!((cycle.start, cycle.period), (1, 3));
+        assert_eq!(cycle.offsets, vec![2]);
+        assert_eq!(part2(&instructions, &network), 5);
+
+        let input = "
+            L
+
+            11A = (11Z, 11Z)
+            11Z = (11B, 11B)
+            11B = (11Z, 11Z)
+            22A = (22Z, 22Z)
+            22Z = (22B, 22B)
+            22B = (22C, 22C)
+            22C = (22B, 22B)
+        "
+        .trim();
+
+        let (instructions, network) = parse(input);
+        assert_eq!(part2(&instructions, &network), 1);
+
+        let cycles = vec![
+            Cycle {
+                start: 0,
+                period: 2,
+                prefix_hits: vec![],
+                offsets: vec![0],
+            },
+            Cycle {
+                start: 0,
+                period: 4,
+                prefix_hits: vec![],
+                offsets: vec![1, 3],
+            },
+        ];
+        assert_eq!(earliest(&cycles), None);
+    }
+
+    #[test]
+    fn test_day8_paths_and_patterns() {
+        let input = "
+            LLR
+
+            AAA = (BBB, BBB)
+            BBB = (AAA, ZZZ)
+            ZZZ = (ZZZ, ZZZ)
+        "
+        .trim();
+
+        let (instructions, network) = parse(input);
+        assert_eq!(
+            network.path(&instructions, network.index("AAA"), |node| node == "ZZZ"),
+            Some(vec!["AAA", "BBB", "AAA", "BBB", "AAA", "BBB", "ZZZ"])
+        );
+        assert_eq!(
+            network.path(&instructions, network.index("ZZZ"), |node| node == "AAA"),
+            None
+        );
+
+        assert!(matches("??A", "11A"));
+        assert!(matches("*Z", "ZZZ"));
+        assert!(matches("A*A", "AA"));
+        assert!(!matches("??A", "1A"));
+        assert!(!matches("A*B", "AAA"));
+        assert_eq!(
+            ghost_walk(
+                &instructions,
+                &network,
+                |node| matches("A*", node),
+                |node| matches("Z??", node),
+            ),
+            Some(6)
+        );
+        assert_eq!(
+            ghost_walk(
+                &instructions,
+                &network,
+                |node| matches("@@@", node),
+                |node| node == "ZZZ",
+            ),
+            None
+        );
+        assert_eq!(earliest(&[]), None);
+    }
+
+    #[test]
+    fn test_day8_dot() {
+        let input = "
+            LLR
+
+            AAA = (BBB, BBB)
+            BBB = (AAA, ZZZ)
+            ZZZ = (ZZZ, ZZZ)
+        "
+        .trim();
+
+        let (_, network) = parse(input);
+        assert_eq!(
+            network.to_dot(),
+            "digraph network {
+    \"AAA\" -> \"BBB\" [label=\"LR\"];
+    \"BBB\" -> \"AAA\" [label=\"L\"];
+    \"BBB\" -> \"ZZZ\" [label=\"R\"];
+    \"ZZZ\" -> \"ZZZ\" [label=\"LR\"];
+}
+"
+        );
     }
 }
@@ -1,18 +1,16 @@
+use std::env;
+
 use adventofcode2023::read_input;
 
-fn restore(numbers: &mut Vec<i32>, start_index: usize, is_opposite: bool) {
-    for i in (1..=start_index).rev() {
-        for j in i..numbers.len() {
-            if !is_opposite {
-                numbers[j] += numbers[j - 1];
-            } else {
-                numbers[j] = numbers[j - 1] - numbers[j];
-            }
-        }
+fn gcd(a: i128, b: i128) -> i128 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
     }
 }
 
-fn step(numbers: &mut Vec<i32>, start_index: usize) -> bool {
+fn step(numbers: &mut [i128], start_index: usize) -> bool {
     let mut all_same = true;
     for i in (start_index + 1..numbers.len()).rev() {
         numbers[i] -= numbers[i - 1];
@@ -23,48 +21,147 @@ fn step(numbers: &mut Vec<i32>, start_index: usize) -> bool {
     all_same
 }
 
-fn process(numbers: &mut Vec<i32>) -> i32 {
-    let mut start_index = 0;
-    while !step(numbers, start_index) {
-        start_index += 1;
+pub struct Polynomial {
+    differences: Vec<i128>,
+}
+
+impl Polynomial {
+    fn differences(numbers: &[i128]) -> (Self, bool) {
+        let mut table = numbers.to_vec();
+        let mut start_index = 0;
+        while start_index + 1 < table.len() && !step(&mut table, start_index) {
+            start_index += 1;
+        }
+        let constant = numbers.windows(2).all(|pair| pair[0] == pair[1]);
+        let settled = numbers.len() >= 2 && (constant || table.len() >= start_index + 3);
+        let mut differences = table[..table.len().min(start_index + 2)].to_vec();
+        while differences.last() == Some(&0) {
+            differences.pop();
+        }
+        (Self { differences }, settled)
+    }
+
+    pub fn fit(numbers: &[i128]) -> Self {
+        Self::differences(numbers).0
+    }
+
+    pub fn try_fit(numbers: &[i128]) -> Result<Self, String> {
+        if numbers.len() < 2 {
+            return Err(format!(
+                "need at least 2 values to fit a polynomial, got {}",
+                numbers.len()
+            ));
+        }
+        match Self::differences(numbers) {
+            (polynomial, true) => Ok(polynomial),
+            _ => Err(format!(
+                "underdetermined: degree ≥ {} with no redundant point to confirm it",
+                numbers.len() - 1
+            )),
+        }
+    }
+
+    pub fn degree(&self) -> usize {
+        self.differences.len().saturating_sub(1)
+    }
+
+    pub fn evaluate(&self, x: i128) -> i128 {
+        let mut result = 0;
+        let mut binomial = 1;
+        for (j, difference) in self.differences.iter().enumerate() {
+            if j > 0 {
+                binomial = binomial * (x - j as i128 + 1) / j as i128;
+            }
+            result += difference * binomial;
+        }
+        result
+    }
+
+    pub fn coefficients(&self) -> Vec<(i128, i128)> {
+        let factorial = (1..=self.degree() as i128).product::<i128>();
+        let mut numerators = vec![0; self.differences.len()];
+        let mut falling = vec![1];
+        let mut j_factorial = 1;
+        for (j, difference) in self.differences.iter().enumerate() {
+            if j > 0 {
+                j_factorial *= j as i128;
+                let mut next = vec![0; falling.len() + 1];
+                for (i, coefficient) in falling.iter().enumerate() {
+                    next[i + 1] += coefficient;
+                    next[i] -= coefficient * (j as i128 - 1);
+                }
+                falling = next;
+            }
+            for (i, coefficient) in falling.iter().enumerate() {
+                numerators[i] += difference * (factorial / j_factorial) * coefficient;
+            }
+        }
+        numerators
+            .into_iter()
+            .map(|numerator| {
+                let divisor = gcd(numerator, factorial).max(1);
+                (numerator / divisor, factorial / divisor)
+            })
+            .collect()
     }
-    numbers.push(*numbers.last().unwrap());
-    restore(numbers, start_index + 1, false);
-    *numbers.last().unwrap()
 }
 
-fn part1(input: &str) -> i32 {
-    let mut result = 0;
-    for line in input.lines() {
-        let mut numbers = line
-            .trim()
-            .split_whitespace()
-            .map(|x| x.parse().unwrap())
-            .collect();
-        result += process(&mut numbers);
-    }
-    result
+fn parse(input: &str) -> Vec<Vec<i128>> {
+    input
+        .lines()
+        .map(|line| {
+            line.split_whitespace()
+                .map(|x| x.parse().unwrap())
+                .collect()
+        })
+        .collect()
+}
+
+pub fn validate(input: &str) -> Vec<Result<usize, String>> {
+    parse(input)
+        .iter()
+        .map(|numbers| Polynomial::try_fit(numbers).map(|polynomial| polynomial.degree()))
+        .collect()
 }
 
-fn part2(input: &str) -> i32 {
+fn process(input: &str, backwards: bool) -> Result<i128, String> {
     let mut result = 0;
-    for line in input.lines() {
-        let mut numbers = line
-            .trim()
-            .split_whitespace()
-            .rev()
-            .map(|x| x.parse().unwrap())
-            .collect();
-        result += process(&mut numbers);
-    }
-    result
+    for (i, numbers) in parse(input).iter().enumerate() {
+        let polynomial =
+            Polynomial::try_fit(numbers).map_err(|error| format!("line {}: {}", i + 1, error))?;
+        let x = if backwards { -1 } else { numbers.len() as i128 };
+        result += polynomial.evaluate(x);
+    }
+    Ok(result)
+}
+
+fn part1(input: &str) -> Result<i128, String> {
+    process(input, false)
+}
+
+fn part2(input: &str) -> Result<i128, String> {
+    process(input, true)
 }
 
 fn main() {
     let input = read_input(9);
 
-    println!("{}", part1(&input));
-    println!("{}", part2(&input));
+    if env::args().nth(1).as_deref() == Some("validate") {
+        for (i, result) in validate(&input).into_iter().enumerate() {
+            match result {
+                Ok(degree) => println!("line {}: degree {}", i + 1, degree),
+                Err(error) => println!("line {}: {}", i + 1, error),
+            }
+        }
+        return;
+    }
+
+    for result in [part1(&input), part2(&input)] {
+        match result {
+            Ok(value) => println!("{}", value),
+            Err(error) => println!("{}", error),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -72,22 +169,26 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_restore() {
-        let mut input = vec![0, 3, 3, 3, 3, 3, 3];
-        restore(&mut input, 1, false);
-        assert_eq!(input, vec![0, 3, 6, 9, 12, 15, 18]);
-
-        input = vec![1, 2, 1, 1, 1, 1, 1];
-        restore(&mut input, 2, false);
-        assert_eq!(input, vec![1, 3, 6, 10, 15, 21, 28]);
+    fn test_polynomial() {
+        let polynomial = Polynomial::fit(&[10, 13, 16, 21, 30, 45]);
+        assert_eq!(polynomial.degree(), 3);
+        assert_eq!(polynomial.evaluate(6), 68);
+        assert_eq!(polynomial.evaluate(-1), 5);
+        assert_eq!(polynomial.evaluate(-2), -4);
+        assert_eq!(
+            polynomial.coefficients(),
+            vec![(10, 1), (11, 3), (-1, 1), (1, 3)]
+        );
 
-        input = vec![10, 3, 0, 2, 2, 2, 2];
-        restore(&mut input, 3, false);
-        assert_eq!(input, vec![10, 13, 16, 21, 30, 45, 68]);
+        let polynomial = Polynomial::fit(&[1, 3, 6, 10, 15, 21]);
+        assert_eq!(polynomial.degree(), 2);
+        assert_eq!(polynomial.evaluate(1_000_000_000), 500_000_001_500_000_001);
+        assert_eq!(polynomial.coefficients(), vec![(1, 1), (3, 2), (1, 2)]);
 
-        input = vec![45, 15, 6, 2, 2, 2, 2];
-        restore(&mut input, 3, true);
-        assert_eq!(input, vec![45, 30, 21, 16, 13, 10, 5]);
+        let polynomial = Polynomial::fit(&[7, 7, 7]);
+        assert_eq!(polynomial.degree(), 0);
+        assert_eq!(polynomial.evaluate(-100), 7);
+        assert_eq!(Polynomial::fit(&[0, 0]).coefficients(), vec![]);
     }
 
     #[test]
@@ -110,7 +211,7 @@ mod tests {
         "
         .trim();
 
-        assert_eq!(part1(input), 114);
+        assert_eq!(part1(input), Ok(114));
     }
 
     #[test]
@@ -122,6 +223,43 @@ mod tests {
         "
         .trim();
 
-        assert_eq!(part2(input), 2);
+        assert_eq!(part2(input), Ok(2));
+    }
+
+    #[test]
+    fn test_day9_validate() {
+        let input = "
+            0 3 6 9 12 15
+            1 2 4 8 16 32
+            5 5
+            7
+        "
+        .trim();
+
+        let error = "underdetermined: degree ≥ 5 with no redundant point to confirm it";
+        assert_eq!(
+            validate(input),
+            vec![
+                Ok(1),
+                Err(error.to_string()),
+                Ok(0),
+                Err("need at least 2 values to fit a polynomial, got 1".to_string()),
+            ]
+        );
+        assert_eq!(part1(input), Err(format!("line 2: {}", error)));
+
+        assert_eq!(
+            validate("1 3\n0 1 4\n0 1 4 9\n1 3 5"),
+            vec![
+                Err(
+                    "underdetermined: degree ≥ 1 with no redundant point to confirm it".to_string()
+                ),
+                Err(
+                    "underdetermined: degree ≥ 2 with no redundant point to confirm it".to_string()
+                ),
+                Ok(2),
+                Ok(1),
+            ]
+        );
     }
 }